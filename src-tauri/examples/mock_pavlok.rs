@@ -0,0 +1,28 @@
+//! Runs the test mock of the Pavlok API so the app can be exercised without a device:
+//!
+//! ```text
+//! cargo run --example mock_pavlok -- 127.0.0.1:8787
+//! PAVLOK_API_BASE_URL=http://127.0.0.1:8787/api/v5 npm run tauri dev
+//! ```
+
+#[path = "../tests/support/mock_pavlok.rs"]
+mod mock_pavlok;
+
+use std::thread;
+use std::time::Duration;
+
+fn main() {
+    let addr = std::env::args().nth(1).unwrap_or_else(|| "127.0.0.1:8787".to_string());
+    let mock = mock_pavlok::MockPavlok::bind(&addr);
+    println!("Mock Pavlok API listening on {}", mock.base_url());
+
+    let mut seen = 0;
+    loop {
+        thread::sleep(Duration::from_millis(250));
+        let requests = mock.requests();
+        for request in &requests[seen..] {
+            println!("{} {} {}", request.method, request.path, request.body);
+        }
+        seen = requests.len();
+    }
+}
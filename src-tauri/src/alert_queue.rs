@@ -0,0 +1,234 @@
+use crate::history;
+use crate::metrics;
+use crate::snooze;
+use crate::webhooks;
+use crate::pavlok::{Intensity, PavlokClient, PavlokError, Stimulus};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::RandomState;
+use std::fs;
+use std::hash::{BuildHasher, Hasher};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+pub const RETRY_MAX_AGE_ENV: &str = "PAVLOK_RETRY_MAX_AGE_SECS";
+const WORKER_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    /// Stimuli older than this are dropped instead of retried: a late zap is worse than none.
+    pub max_age: Duration,
+    /// Fraction of the delay randomized in both directions, so clients don't retry in lockstep.
+    pub jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            base_delay: Duration::from_secs(5),
+            max_delay: Duration::from_secs(120),
+            max_age: Duration::from_secs(5 * 60),
+            jitter: 0.2,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn from_env() -> Self {
+        let mut policy = Self::default();
+        if let Some(seconds) = std::env::var(RETRY_MAX_AGE_ENV).ok().and_then(|v| v.trim().parse().ok()) {
+            policy.max_age = Duration::from_secs(seconds);
+        }
+        policy
+    }
+
+    /// Delay before retry number `attempts` (1-based). `jitter_sample` is in `[0, 1)`.
+    pub fn backoff(&self, attempts: u32, jitter_sample: f64) -> Duration {
+        let exponent = attempts.saturating_sub(1).min(16);
+        let delay = self.base_delay.saturating_mul(1 << exponent).min(self.max_delay);
+        let spread = 1.0 + self.jitter * (jitter_sample * 2.0 - 1.0);
+        delay.mul_f64(spread.max(0.0))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueuedStimulus {
+    pub stimulus: Stimulus,
+    pub intensity: Intensity,
+    pub reason: String,
+    pub created_at_ms: u64,
+    pub attempts: u32,
+    pub next_attempt_at_ms: u64,
+}
+
+/// Failed stimuli waiting for another attempt. Persisted on every change so
+/// a restart doesn't lose them; the API token is never written to disk.
+pub struct StimulusQueue {
+    path: Option<PathBuf>,
+    policy: RetryPolicy,
+    items: Vec<QueuedStimulus>,
+    token: Option<String>,
+}
+
+impl StimulusQueue {
+    pub fn new(path: Option<PathBuf>, policy: RetryPolicy) -> Self {
+        Self { path, policy, items: Vec::new(), token: None }
+    }
+
+    pub fn load(path: Option<PathBuf>, policy: RetryPolicy) -> Self {
+        let items = path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|raw| serde_json::from_str(&raw).ok())
+            .unwrap_or_default();
+        Self { path, policy, items, token: None }
+    }
+
+    pub fn items(&self) -> &[QueuedStimulus] {
+        &self.items
+    }
+
+    pub fn set_token(&mut self, token: &str) {
+        self.token = Some(token.to_string()).filter(|token| !token.trim().is_empty());
+    }
+
+    pub fn token(&self) -> Option<&str> {
+        self.token.as_deref()
+    }
+
+    /// Queues a stimulus whose first send failed. Only transient failures are
+    /// retried; a pending entry for the same stimulus and reason is replaced.
+    pub fn push_failed(&mut self, stimulus: Stimulus, intensity: Intensity, reason: &str, error: &PavlokError, now_ms: u64) -> bool {
+        if !is_retryable(error) {
+            return false;
+        }
+        self.items.retain(|item| !(item.stimulus == stimulus && item.reason == reason));
+        let mut item = QueuedStimulus {
+            stimulus,
+            intensity,
+            reason: reason.to_string(),
+            created_at_ms: now_ms,
+            attempts: 1,
+            next_attempt_at_ms: now_ms,
+        };
+        item.next_attempt_at_ms = self.next_attempt_at(&item, error, now_ms);
+        self.items.push(item);
+        self.save();
+        true
+    }
+
+    /// A direct send went through, so older copies of that alert are moot.
+    pub fn delivered(&mut self, stimulus: Stimulus, reason: &str) {
+        let before = self.items.len();
+        self.items.retain(|item| !(item.stimulus == stimulus && item.reason == reason));
+        if self.items.len() != before {
+            self.save();
+        }
+    }
+
+    /// Drops stale entries and takes the next entry whose retry time has come.
+    pub fn take_due(&mut self, now_ms: u64) -> Option<QueuedStimulus> {
+        let max_age_ms = self.policy.max_age.as_millis() as u64;
+        let before = self.items.len();
+        self.items.retain(|item| now_ms.saturating_sub(item.created_at_ms) <= max_age_ms);
+
+        let due = self.items.iter().position(|item| item.next_attempt_at_ms <= now_ms);
+        let item = due.map(|index| self.items.remove(index));
+        if item.is_some() || self.items.len() != before {
+            self.save();
+        }
+        item
+    }
+
+    /// Puts a retried entry back after another failure, unless the failure is final.
+    pub fn retry_failed(&mut self, mut item: QueuedStimulus, error: &PavlokError, now_ms: u64) {
+        if !is_retryable(error) {
+            return;
+        }
+        item.attempts += 1;
+        item.next_attempt_at_ms = self.next_attempt_at(&item, error, now_ms);
+        self.items.push(item);
+        self.save();
+    }
+
+    fn next_attempt_at(&self, item: &QueuedStimulus, error: &PavlokError, now_ms: u64) -> u64 {
+        let mut delay = self.policy.backoff(item.attempts, jitter_sample());
+        if let PavlokError::RateLimited { retry_after: Some(seconds) } = error {
+            delay = delay.max(Duration::from_secs(*seconds));
+        }
+        now_ms + delay.as_millis() as u64
+    }
+
+    fn save(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        if let Some(parent) = path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        if let Ok(json) = serde_json::to_string(&self.items) {
+            let _ = fs::write(path, json);
+        }
+    }
+}
+
+pub fn is_retryable(error: &PavlokError) -> bool {
+    match error {
+        PavlokError::Network { .. } | PavlokError::RateLimited { .. } => true,
+        PavlokError::Server { status } => *status >= 500,
+        PavlokError::Unauthorized | PavlokError::InvalidToken => false,
+    }
+}
+
+pub fn now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
+}
+
+// std's RandomState is seeded per instance, which is plenty for jitter.
+fn jitter_sample() -> f64 {
+    let bits = RandomState::new().build_hasher().finish();
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+pub fn queue_file(app: &AppHandle) -> Option<PathBuf> {
+    let mut dir = app.path().app_data_dir().ok()?;
+    dir.push("stimulus-queue.json");
+    Some(dir)
+}
+
+/// Retries queued stimuli in the background as they come due.
+pub fn spawn_worker(app: AppHandle) {
+    thread::spawn(move || loop {
+        thread::sleep(WORKER_INTERVAL);
+        // Held while snoozed; anything past its max age is dropped once the snooze ends.
+        if snooze::is_snoozed(&app) {
+            continue;
+        }
+
+        let (item, token) = {
+            let queue = app.state::<Mutex<StimulusQueue>>();
+            let mut queue = queue.lock().unwrap();
+            // Without a token there is nothing to retry with; keep entries until one arrives.
+            let Some(token) = queue.token().map(str::to_string) else {
+                continue;
+            };
+            match queue.take_due(now_ms()) {
+                Some(item) => (item, token),
+                None => continue,
+            }
+        };
+
+        let result = app.state::<PavlokClient>().send_stimulus(&token, item.stimulus, item.intensity, &item.reason);
+        history::record_stimulus(&app, item.stimulus, item.intensity, &item.reason, &result, true);
+        metrics::record_stimulus(&app, item.stimulus, &result);
+        webhooks::stimulus(&app, item.stimulus, item.intensity, &result);
+        if let Err(error) = result {
+            app.state::<Mutex<StimulusQueue>>().lock().unwrap().retry_failed(item, &error, now_ms());
+        }
+    });
+}
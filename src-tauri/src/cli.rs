@@ -0,0 +1,248 @@
+use crate::alert_queue::now_ms;
+use crate::fatigue::{FatigueEngine, FatigueSnapshot};
+use crate::monitor;
+use crate::pavlok::Stimulus;
+use crate::settings::SettingsStore;
+use crate::snooze::{self, Snooze, SnoozeDuration};
+use crate::tray_status;
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::process::Command;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+pub const USAGE: &str = "usage: pavlok-touch-grass [--start | --stop | --reset | --snooze <minutes> | --status] [--json]";
+const REPLY_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CliCommand {
+    Start,
+    Stop,
+    Reset,
+    Snooze { minutes: u32 },
+    Status,
+}
+
+/// A command for the running instance. `reply_to` and `nonce` are added by
+/// [`run_client`] when it relaunches itself, so the instance knows where to answer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CliRequest {
+    pub command: CliCommand,
+    pub json: bool,
+    pub reply_to: Option<SocketAddr>,
+    pub nonce: Option<String>,
+}
+
+/// Reads the control flags from `args` (program name first). `Ok(None)` means a
+/// plain launch; unknown arguments are ignored since the OS may add its own.
+pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Option<CliRequest>, String> {
+    let mut command = None;
+    let mut json = false;
+    let mut reply_to = None;
+    let mut nonce = None;
+    let mut args = args.into_iter().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--start" => set_command(&mut command, CliCommand::Start)?,
+            "--stop" => set_command(&mut command, CliCommand::Stop)?,
+            "--reset" => set_command(&mut command, CliCommand::Reset)?,
+            "--status" => set_command(&mut command, CliCommand::Status)?,
+            "--snooze" => {
+                let minutes = args
+                    .next()
+                    .and_then(|value| value.parse().ok())
+                    .ok_or_else(|| format!("--snooze needs a number of minutes\n{USAGE}"))?;
+                set_command(&mut command, CliCommand::Snooze { minutes })?;
+            }
+            "--json" => json = true,
+            "--reply-to" => {
+                let addr: SocketAddr = args.next().and_then(|value| value.parse().ok()).ok_or("--reply-to needs an address")?;
+                // Only ever answer on this machine.
+                if !addr.ip().is_loopback() {
+                    return Err("--reply-to must be a loopback address".into());
+                }
+                reply_to = Some(addr);
+            }
+            "--reply-nonce" => nonce = args.next(),
+            _ => {}
+        }
+    }
+
+    // `--json` on its own asks for the status.
+    let command = match command {
+        Some(command) => command,
+        None if json => CliCommand::Status,
+        None => return Ok(None),
+    };
+    Ok(Some(CliRequest { command, json, reply_to, nonce }))
+}
+
+fn set_command(command: &mut Option<CliCommand>, next: CliCommand) -> Result<(), String> {
+    if command.is_some() {
+        return Err(format!("only one command at a time\n{USAGE}"));
+    }
+    *command = Some(next);
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusReport {
+    #[serde(flatten)]
+    pub state: FatigueSnapshot,
+    pub alert_mode: Stimulus,
+    pub snoozed_until_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CliReply {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    pub ok: bool,
+    pub message: String,
+    pub status: StatusReport,
+}
+
+/// Runs `request` in this (the primary) instance and sends the answer back
+/// to the waiting command line, if there is one.
+pub fn handle(app: &AppHandle, request: CliRequest) {
+    let reply = execute(app, &request);
+    if let Some(addr) = request.reply_to {
+        thread::spawn(move || {
+            if let Ok(mut stream) = TcpStream::connect_timeout(&addr, Duration::from_secs(2)) {
+                if let Ok(line) = serde_json::to_string(&reply) {
+                    let _ = writeln!(stream, "{line}");
+                }
+            }
+        });
+    }
+}
+
+pub fn execute(app: &AppHandle, request: &CliRequest) -> CliReply {
+    let result = match request.command {
+        CliCommand::Start => {
+            monitor::set_monitoring(app, true);
+            Ok("Monitoring started".to_string())
+        }
+        CliCommand::Stop => {
+            monitor::set_monitoring(app, false);
+            Ok("Monitoring stopped".to_string())
+        }
+        CliCommand::Reset => {
+            monitor::reset(app);
+            Ok("Fatigue reset".to_string())
+        }
+        CliCommand::Snooze { minutes } => {
+            snooze::start(app, SnoozeDuration::Minutes { minutes }).map(|_| format!("Alerts snoozed for {minutes} min"))
+        }
+        CliCommand::Status => Ok(String::new()),
+    };
+    let status = status_report(app);
+    let snoozed_minutes = status.snoozed_until_ms.map(|until| until.saturating_sub(now_ms()).div_ceil(60_000));
+    let summary = tray_status::tooltip(&status.state, snoozed_minutes);
+    let (ok, message) = match result {
+        Ok(done) if done.is_empty() => (true, summary),
+        Ok(done) => (true, format!("{done}\n{summary}")),
+        Err(error) => (false, error),
+    };
+    CliReply { nonce: request.nonce.clone(), ok, message, status }
+}
+
+pub fn status_report(app: &AppHandle) -> StatusReport {
+    let state = app.state::<Mutex<FatigueEngine>>().lock().unwrap().snapshot();
+    let alert_mode = app
+        .try_state::<Mutex<SettingsStore>>()
+        .map_or(Stimulus::Beep, |store| store.lock().unwrap().get().alert_mode);
+    let snoozed_until_ms = app.try_state::<Mutex<Snooze>>().and_then(|snooze| {
+        let snooze = snooze.lock().unwrap();
+        snooze.state().until_ms.filter(|_| snooze.is_active(now_ms()))
+    });
+    StatusReport { state, alert_mode, snoozed_until_ms }
+}
+
+/// What the command line prints, parsed loosely so it doesn't need the engine types.
+#[derive(Debug, Deserialize)]
+struct ReceivedReply {
+    nonce: Option<String>,
+    ok: bool,
+    message: String,
+    status: serde_json::Value,
+}
+
+/// Command-line side: relaunches this executable with a reply address so the
+/// single-instance plugin forwards the command to the running app (or, if none
+/// is running, the relaunched copy becomes the app), then prints the answer.
+/// Returns the process exit code.
+pub fn run_client(args: &[String], json: bool) -> i32 {
+    attach_console();
+    match request_reply(args) {
+        Ok(reply) => {
+            if json {
+                let out = serde_json::json!({ "ok": reply.ok, "message": reply.message, "status": reply.status });
+                println!("{out}");
+            } else if reply.ok {
+                println!("{}", reply.message);
+            } else {
+                eprintln!("{}", reply.message);
+            }
+            if reply.ok {
+                0
+            } else {
+                1
+            }
+        }
+        Err(error) => {
+            eprintln!("{error}");
+            1
+        }
+    }
+}
+
+fn request_reply(args: &[String]) -> Result<ReceivedReply, String> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).map_err(|e| e.to_string())?;
+    let addr = listener.local_addr().map_err(|e| e.to_string())?;
+    let nonce = uuid::Uuid::new_v4().to_string();
+    let exe = std::env::current_exe().map_err(|e| e.to_string())?;
+    Command::new(exe)
+        .args(args.iter().skip(1))
+        .arg("--reply-to")
+        .arg(addr.to_string())
+        .arg("--reply-nonce")
+        .arg(&nonce)
+        .spawn()
+        .map_err(|e| e.to_string())?;
+
+    listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+    let deadline = Instant::now() + REPLY_TIMEOUT;
+    while Instant::now() < deadline {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                let _ = stream.set_nonblocking(false);
+                let _ = stream.set_read_timeout(Some(Duration::from_secs(2)));
+                let mut line = String::new();
+                let _ = BufReader::new(stream).read_line(&mut line);
+                // Anything else on this machine could connect; only trust our own nonce.
+                match serde_json::from_str::<ReceivedReply>(&line) {
+                    Ok(reply) if reply.nonce.as_deref() == Some(nonce.as_str()) => return Ok(reply),
+                    _ => continue,
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => thread::sleep(Duration::from_millis(50)),
+            Err(e) => return Err(e.to_string()),
+        }
+    }
+    Err("no reply from Pavlok: Touch Grass".into())
+}
+
+/// Release builds use the Windows GUI subsystem, which has no console to print to.
+pub fn attach_console() {
+    #[cfg(target_os = "windows")]
+    unsafe {
+        use windows::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
+        let _ = AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
@@ -0,0 +1,138 @@
+use crate::intensity::Intensities;
+use crate::pavlok::{Intensity, Stimulus};
+use crate::settings::{self, SettingsPatch};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use tauri::menu::CheckMenuItem;
+use tauri::{AppHandle, Manager, Wry};
+
+pub const MENU_ID: &str = "escalation";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EscalationStep {
+    pub stimulus: Stimulus,
+    /// Minutes of fatigue past the work limit before this step kicks in.
+    pub after_minutes: u32,
+}
+
+/// How limit alerts escalate while the user keeps working past 100%.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct EscalationPolicy {
+    /// When off, every limit alert uses the widget's alert mode as before.
+    pub enabled: bool,
+    pub steps: Vec<EscalationStep>,
+    /// Added to the stimulus intensity on each repeated alert within a step.
+    pub ramp: u32,
+    /// No alert is ever sent above this, whatever the step or ramp says.
+    pub ceiling: Intensity,
+}
+
+impl Default for EscalationPolicy {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            steps: vec![
+                EscalationStep { stimulus: Stimulus::Beep, after_minutes: 0 },
+                EscalationStep { stimulus: Stimulus::Vibe, after_minutes: 5 },
+                EscalationStep { stimulus: Stimulus::Zap, after_minutes: 10 },
+            ],
+            ramp: 10,
+            ceiling: Intensity::new(50).unwrap(),
+        }
+    }
+}
+
+impl EscalationPolicy {
+    pub fn validate(&self) -> Result<(), String> {
+        let Some(first) = self.steps.first() else {
+            return Err("escalation needs at least one step".to_string());
+        };
+        if first.after_minutes != 0 {
+            return Err("the first escalation step must start at the limit (afterMinutes = 0)".to_string());
+        }
+        if self.steps.windows(2).any(|pair| pair[1].after_minutes <= pair[0].after_minutes) {
+            return Err("escalation steps must be in increasing afterMinutes order".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Picks the stimulus for each limit alert. The ladder only climbs until
+/// [`Escalation::reset`], which the app calls once the user has rested.
+pub struct Escalation {
+    policy: EscalationPolicy,
+    step: Option<usize>,
+    repeats: u32,
+}
+
+impl Escalation {
+    pub fn new(policy: EscalationPolicy) -> Self {
+        Self { policy, step: None, repeats: 0 }
+    }
+
+    pub fn policy(&self) -> &EscalationPolicy {
+        &self.policy
+    }
+
+    /// Swapping the policy restarts the ladder, since old step indexes no longer apply.
+    pub fn set_policy(&mut self, policy: EscalationPolicy) {
+        self.policy = policy;
+        self.reset();
+    }
+
+    pub fn reset(&mut self) {
+        self.step = None;
+        self.repeats = 0;
+    }
+
+    /// Stimulus and intensity for the next limit alert, `overshoot_minutes`
+    /// past the work limit. Falls back to `mode` when escalation is off.
+    pub fn next_alert(&mut self, mode: Stimulus, overshoot_minutes: u32, intensities: &Intensities) -> (Stimulus, Intensity) {
+        if !self.policy.enabled || self.policy.steps.is_empty() {
+            return (mode, intensities.get(mode));
+        }
+
+        let reached = self
+            .policy
+            .steps
+            .iter()
+            .rposition(|step| step.after_minutes <= overshoot_minutes)
+            .unwrap_or(0);
+        match self.step {
+            Some(step) if step >= reached => self.repeats += 1,
+            _ => {
+                self.step = Some(reached);
+                self.repeats = 0;
+            }
+        }
+
+        let stimulus = self.policy.steps[self.step.unwrap_or(reached)].stimulus;
+        let ramped = intensities.get(stimulus).value().saturating_add(self.policy.ramp.saturating_mul(self.repeats));
+        let level = ramped.min(self.policy.ceiling.value());
+        (stimulus, Intensity::new(level).unwrap_or(self.policy.ceiling))
+    }
+}
+
+/// Stores a new policy through the settings file, which also updates the tray toggle.
+pub fn update(app: &AppHandle, policy: EscalationPolicy) -> Result<EscalationPolicy, String> {
+    let patch = SettingsPatch { escalation: Some(policy), ..SettingsPatch::default() };
+    settings::update(app, patch).map(|settings| settings.escalation)
+}
+
+/// Flips escalation on or off from the tray, keeping the configured steps.
+pub fn toggle(app: &AppHandle) {
+    let mut policy = app.state::<Mutex<Escalation>>().lock().unwrap().policy().clone();
+    policy.enabled = !policy.enabled;
+    let _ = update(app, policy);
+}
+
+/// Tray check item mirroring `EscalationPolicy::enabled`.
+pub struct EscalationToggle(pub CheckMenuItem<Wry>);
+
+impl EscalationToggle {
+    pub fn build<M: Manager<Wry>>(manager: &M, policy: &EscalationPolicy) -> tauri::Result<Self> {
+        CheckMenuItem::with_id(manager, MENU_ID, "Escalating Alerts", true, policy.enabled, None::<&str>).map(Self)
+    }
+}
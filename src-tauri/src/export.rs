@@ -0,0 +1,148 @@
+use crate::history::{History, HistoryRecord};
+use crate::stats::StatsRange;
+use chrono::{DateTime, Local, SecondsFormat, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_opener::OpenerExt;
+
+pub const MENU_ID: &str = "export_history";
+
+const CSV_HEADER: &str = "type,atMs,time,activeSeconds,active,fatigue,restStreak,monitoring,workMinutes,limitReached,rested,stimulus,intensity,reason,error,retry";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ExportFormat {
+    /// One row per record; minute and stimulus columns are left empty where they don't apply.
+    Csv,
+    /// The records exactly as `history.jsonl` stores them.
+    Jsonl,
+}
+
+impl ExportFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Jsonl => "jsonl",
+        }
+    }
+}
+
+pub fn write_records(format: ExportFormat, records: &[HistoryRecord], out: &mut impl Write) -> io::Result<()> {
+    match format {
+        ExportFormat::Csv => {
+            writeln!(out, "{CSV_HEADER}")?;
+            for record in records {
+                writeln!(out, "{}", csv_row(record))?;
+            }
+        }
+        ExportFormat::Jsonl => {
+            for record in records {
+                writeln!(out, "{}", serde_json::to_string(record).map_err(io::Error::other)?)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Writes the records in `range` (everything without one) to `path` and
+/// returns how many there were. The history is only locked while it is read,
+/// so a slow disk doesn't hold up the sampler.
+pub fn export(history: &Mutex<History>, format: ExportFormat, range: Option<StatsRange>, path: &Path) -> io::Result<usize> {
+    let records = history.lock().unwrap().query(range.map(|range| range.from_ms), range.map(|range| range.to_ms));
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut out = BufWriter::new(fs::File::create(path)?);
+    write_records(format, &records, &mut out)?;
+    out.flush()?;
+    Ok(records.len())
+}
+
+/// Rejects targets for `export_history` other than an absolute path ending in
+/// the format's extension, so the command can't be used to overwrite
+/// arbitrary files such as settings or scripts.
+pub fn check_target(path: &Path, format: ExportFormat) -> Result<(), String> {
+    if !path.is_absolute() {
+        return Err(format!("export path must be absolute, got {}", path.display()));
+    }
+    let extension = path.extension().and_then(|extension| extension.to_str()).unwrap_or_default();
+    if !extension.eq_ignore_ascii_case(format.extension()) {
+        return Err(format!("export path must end in .{}, got {}", format.extension(), path.display()));
+    }
+    Ok(())
+}
+
+/// Tray action: the whole history as CSV into Downloads, then shown in the file manager.
+pub fn export_from_tray(app: &AppHandle) {
+    let Some(dir) = app.path().download_dir().ok().or_else(|| app.path().app_data_dir().ok()) else {
+        return;
+    };
+    let path = default_file_name(&dir, ExportFormat::Csv, crate::alert_queue::now_ms());
+    let result = export(&app.state::<Mutex<History>>(), ExportFormat::Csv, None, &path);
+    if result.is_ok() {
+        let _ = app.opener().reveal_item_in_dir(&path);
+    }
+}
+
+/// `pavlok-history-<local date>` in `dir`, with `-2`, `-3`, ... appended when
+/// an earlier export already took the name.
+pub fn default_file_name(dir: &Path, format: ExportFormat, now_ms: u64) -> PathBuf {
+    let date = Local.timestamp_millis_opt(now_ms.min(i64::MAX as u64) as i64).single().unwrap_or_default();
+    let (date, extension) = (date.format("%Y-%m-%d"), format.extension());
+    let mut path = dir.join(format!("pavlok-history-{date}.{extension}"));
+    let mut copy = 2;
+    while path.exists() {
+        path = dir.join(format!("pavlok-history-{date}-{copy}.{extension}"));
+        copy += 1;
+    }
+    path
+}
+
+fn csv_row(record: &HistoryRecord) -> String {
+    let mut cells: Vec<String> = vec![String::new(); 16];
+    match record {
+        HistoryRecord::Minute { at_ms, sample } => {
+            cells[0] = "minute".into();
+            cells[1] = at_ms.to_string();
+            cells[2] = utc_timestamp(*at_ms);
+            cells[3] = sample.active_seconds.to_string();
+            cells[4] = sample.active.to_string();
+            cells[5] = sample.fatigue.to_string();
+            cells[6] = sample.rest_streak.to_string();
+            cells[7] = sample.monitoring.to_string();
+            cells[8] = sample.work_minutes.to_string();
+            cells[9] = sample.limit_reached.to_string();
+            cells[10] = sample.rested.to_string();
+        }
+        HistoryRecord::Stimulus { at_ms, stimulus, intensity, reason, error, retry } => {
+            cells[0] = "stimulus".into();
+            cells[1] = at_ms.to_string();
+            cells[2] = utc_timestamp(*at_ms);
+            cells[11] = stimulus.as_str().into();
+            cells[12] = intensity.value().to_string();
+            cells[13] = reason.clone();
+            cells[14] = error.as_ref().map(ToString::to_string).unwrap_or_default();
+            cells[15] = retry.to_string();
+        }
+    }
+    cells.iter().map(|cell| csv_escape(cell)).collect::<Vec<_>>().join(",")
+}
+
+fn csv_escape(cell: &str) -> String {
+    if cell.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell.to_string()
+    }
+}
+
+/// `YYYY-MM-DDTHH:MM:SSZ`; spreadsheets parse it without help.
+pub fn utc_timestamp(ms: u64) -> String {
+    DateTime::<Utc>::from_timestamp_millis(ms.min(i64::MAX as u64) as i64)
+        .unwrap_or_default()
+        .to_rfc3339_opts(SecondsFormat::Secs, true)
+}
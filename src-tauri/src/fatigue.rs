@@ -0,0 +1,323 @@
+use crate::idle::IdleSource;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+// A minute counts as "work" once at least this many of its seconds were active.
+pub const ACTIVE_SECONDS_PER_MINUTE: u32 = 10;
+// A sample counts as active when the last input happened less than this long ago.
+pub const ACTIVE_IDLE_THRESHOLD_SECS: f64 = 2.0;
+pub const ALERT_COOLDOWN: Duration = Duration::from_secs(60);
+pub const DEFAULT_WORK_MINUTES: u32 = 45;
+pub const DEFAULT_BREAK_MINUTES: u32 = 5;
+
+pub trait Clock: Send {
+    fn now(&self) -> Instant;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum EngineEvent {
+    /// Fatigue is at the limit while monitoring and the alert cooldown has passed.
+    LimitReached,
+    /// Fatigue dropped back to zero after being positive.
+    Rested,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FatigueSnapshot {
+    pub fatigue: u32,
+    pub percent: f64,
+    pub rest_streak: u32,
+    pub monitoring: bool,
+    pub at_limit: bool,
+    pub work_minutes: u32,
+    pub break_minutes: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FatigueUpdate {
+    pub state: FatigueSnapshot,
+    pub events: Vec<EngineEvent>,
+}
+
+/// One finished minute, kept for the activity history.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MinuteSample {
+    pub active_seconds: u32,
+    /// Counted as a work minute (at least `ACTIVE_SECONDS_PER_MINUTE` active seconds).
+    pub active: bool,
+    pub fatigue: u32,
+    pub rest_streak: u32,
+    pub monitoring: bool,
+    pub work_minutes: u32,
+    /// A `LimitReached` event fired at the end of this minute.
+    pub limit_reached: bool,
+    /// A `Rested` event fired at the end of this minute.
+    pub rested: bool,
+}
+
+/// What survives a restart. `saved_at_ms` is wall-clock time because
+/// `Instant`s mean nothing to the next process.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PersistedState {
+    pub fatigue: u32,
+    pub rest_streak: u32,
+    pub monitoring: bool,
+    pub saved_at_ms: u64,
+}
+
+pub struct FatigueEngine {
+    clock: Box<dyn Clock>,
+    idle: Box<dyn IdleSource>,
+    work_minutes: u32,
+    break_minutes: u32,
+    monitoring: bool,
+    fatigue: u32,
+    rest_streak: u32,
+    active_seconds: u32,
+    second_counter: u32,
+    last_tick_at: Option<Instant>,
+    last_alert_at: Option<Instant>,
+    finished_minutes: Vec<MinuteSample>,
+}
+
+impl FatigueEngine {
+    pub fn new(clock: Box<dyn Clock>, idle: Box<dyn IdleSource>) -> Self {
+        Self {
+            clock,
+            idle,
+            work_minutes: DEFAULT_WORK_MINUTES,
+            break_minutes: DEFAULT_BREAK_MINUTES,
+            monitoring: false,
+            fatigue: 0,
+            rest_streak: 0,
+            active_seconds: 0,
+            second_counter: 0,
+            last_tick_at: None,
+            last_alert_at: None,
+            finished_minutes: Vec::new(),
+        }
+    }
+
+    pub fn set_limits(&mut self, work_minutes: u32, break_minutes: u32) {
+        self.work_minutes = work_minutes.max(1);
+        self.break_minutes = break_minutes.max(1);
+    }
+
+    pub fn set_monitoring(&mut self, enabled: bool) {
+        // A repeated start must not throw away the minute in progress.
+        if enabled == self.monitoring {
+            return;
+        }
+        self.monitoring = enabled;
+        if enabled {
+            // New run starts a fresh minute window; fatigue value is preserved.
+            self.active_seconds = 0;
+            self.second_counter = 0;
+            self.last_tick_at = Some(self.clock.now());
+        } else {
+            self.last_tick_at = None;
+        }
+    }
+
+    pub fn reset(&mut self) -> Vec<EngineEvent> {
+        let previous_fatigue = self.fatigue;
+        self.fatigue = 0;
+        self.rest_streak = 0;
+        self.active_seconds = 0;
+        self.second_counter = 0;
+        self.last_alert_at = None;
+        self.last_tick_at = Some(self.clock.now());
+
+        if previous_fatigue > 0 {
+            vec![EngineEvent::Rested]
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Takes one idle sample and folds the time elapsed since the previous tick
+    /// into the minute accounting. Meant to be called about once per second.
+    pub fn tick(&mut self) -> Vec<EngineEvent> {
+        let now = self.clock.now();
+        let elapsed_seconds = match self.last_tick_at {
+            Some(previous) => now.saturating_duration_since(previous).as_secs().max(1) as u32,
+            None => 1,
+        };
+        self.last_tick_at = Some(now);
+
+        let mut events = Vec::new();
+        match self.idle.idle_seconds() {
+            Some(idle_seconds) => {
+                // Missed ticks are timing jitter/lag; keep minute accounting.
+                let missed_seconds = elapsed_seconds - 1;
+                if missed_seconds > 0 {
+                    self.apply_sample_seconds(0, missed_seconds, now, &mut events);
+                }
+                let active_now = idle_seconds < ACTIVE_IDLE_THRESHOLD_SECS;
+                if active_now {
+                    self.apply_sample_seconds(1, 0, now, &mut events);
+                } else {
+                    self.apply_sample_seconds(0, 1, now, &mut events);
+                }
+            }
+            None => {
+                // If the idle probe fails (e.g. during lock/unlock), treat the
+                // elapsed time as inactivity so recovery continues.
+                self.apply_sample_seconds(0, elapsed_seconds, now, &mut events);
+            }
+        }
+        events
+    }
+
+    pub fn snapshot(&self) -> FatigueSnapshot {
+        FatigueSnapshot {
+            fatigue: self.fatigue,
+            percent: self.fatigue as f64 / self.work_minutes as f64 * 100.0,
+            rest_streak: self.rest_streak,
+            monitoring: self.monitoring,
+            at_limit: self.is_at_limit(),
+            work_minutes: self.work_minutes,
+            break_minutes: self.break_minutes,
+        }
+    }
+
+    pub fn persisted_state(&self, now_ms: u64) -> PersistedState {
+        PersistedState {
+            fatigue: self.fatigue,
+            rest_streak: self.rest_streak,
+            monitoring: self.monitoring,
+            saved_at_ms: now_ms,
+        }
+    }
+
+    /// Picks up where a previous run left off. The time the app was not running
+    /// counts as rest, so a quick restart keeps fatigue but a night off clears it.
+    /// Set the limits first: the break length decides whether downtime was a full rest.
+    pub fn restore(&mut self, state: &PersistedState, now_ms: u64) {
+        self.fatigue = state.fatigue;
+        self.rest_streak = state.rest_streak;
+        self.last_alert_at = None;
+        let downtime_minutes = now_ms.saturating_sub(state.saved_at_ms) / 60_000;
+        self.apply_rest(downtime_minutes.min(u32::MAX as u64) as u32);
+        self.set_monitoring(state.monitoring);
+    }
+
+    /// Counts `minutes` the engine could not observe (shutdown, suspend) as idle minutes.
+    pub fn apply_rest(&mut self, minutes: u32) -> Vec<EngineEvent> {
+        if minutes == 0 {
+            return Vec::new();
+        }
+        let previous_fatigue = self.fatigue;
+        self.fatigue = self.fatigue.saturating_sub(minutes);
+        self.rest_streak = self.rest_streak.saturating_add(minutes);
+        if self.rest_streak >= self.break_minutes {
+            self.fatigue = 0;
+        }
+        // Whatever was counted of the current minute predates the gap.
+        self.active_seconds = 0;
+        self.second_counter = 0;
+
+        if previous_fatigue > 0 && self.fatigue == 0 {
+            vec![EngineEvent::Rested]
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Minutes completed since the last call, oldest first.
+    pub fn take_finished_minutes(&mut self) -> Vec<MinuteSample> {
+        std::mem::take(&mut self.finished_minutes)
+    }
+
+    pub fn probe_idle(&self) -> Option<f64> {
+        self.idle.idle_seconds()
+    }
+
+    pub fn is_at_limit(&self) -> bool {
+        self.fatigue >= self.work_minutes
+    }
+
+    fn apply_sample_seconds(&mut self, active_seconds: u32, inactive_seconds: u32, now: Instant, events: &mut Vec<EngineEvent>) {
+        let total_seconds = active_seconds + inactive_seconds;
+        if total_seconds == 0 {
+            return;
+        }
+        self.active_seconds += active_seconds;
+
+        let mut remaining = total_seconds;
+        while remaining > 0 {
+            let step = remaining.min(60 - self.second_counter);
+            self.second_counter += step;
+            remaining -= step;
+
+            if self.second_counter >= 60 {
+                self.apply_minute(now, events);
+            }
+        }
+    }
+
+    fn apply_minute(&mut self, now: Instant, events: &mut Vec<EngineEvent>) {
+        let previous_fatigue = self.fatigue;
+        let active_seconds = self.active_seconds.min(60);
+        let active = self.active_seconds >= ACTIVE_SECONDS_PER_MINUTE;
+
+        if active {
+            // While paused, activity should not increase fatigue.
+            if self.monitoring {
+                self.fatigue += 1;
+            }
+            self.rest_streak = 0;
+        } else {
+            self.fatigue = self.fatigue.saturating_sub(1);
+            self.rest_streak += 1;
+        }
+
+        self.active_seconds = 0;
+        self.second_counter = 0;
+
+        if self.rest_streak >= self.break_minutes {
+            self.fatigue = 0;
+        }
+
+        let rested = previous_fatigue > 0 && self.fatigue == 0;
+        if rested {
+            events.push(EngineEvent::Rested);
+        }
+
+        let mut limit_reached = false;
+        if self.is_at_limit() && self.monitoring {
+            let cooled_down = self
+                .last_alert_at
+                .is_none_or(|sent_at| now.saturating_duration_since(sent_at) >= ALERT_COOLDOWN);
+            if cooled_down {
+                events.push(EngineEvent::LimitReached);
+                self.last_alert_at = Some(now);
+                limit_reached = true;
+            }
+        }
+
+        self.finished_minutes.push(MinuteSample {
+            active_seconds,
+            active,
+            fatigue: self.fatigue,
+            rest_streak: self.rest_streak,
+            monitoring: self.monitoring,
+            work_minutes: self.work_minutes,
+            limit_reached,
+            rested,
+        });
+    }
+}
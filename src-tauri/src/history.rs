@@ -0,0 +1,175 @@
+use crate::fatigue::MinuteSample;
+use crate::pavlok::{Intensity, PavlokError, Stimulus};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+pub const DEFAULT_RETENTION_DAYS: u32 = 90;
+const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+
+/// One line of `history.jsonl`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub enum HistoryRecord {
+    Minute {
+        at_ms: u64,
+        #[serde(flatten)]
+        sample: MinuteSample,
+    },
+    Stimulus {
+        at_ms: u64,
+        stimulus: Stimulus,
+        intensity: Intensity,
+        reason: String,
+        /// `None` when the API accepted it.
+        error: Option<PavlokError>,
+        /// Sent by the retry queue rather than directly.
+        retry: bool,
+    },
+}
+
+impl HistoryRecord {
+    pub fn at_ms(&self) -> u64 {
+        match self {
+            HistoryRecord::Minute { at_ms, .. } | HistoryRecord::Stimulus { at_ms, .. } => *at_ms,
+        }
+    }
+}
+
+/// Append-only JSON-lines log of finished minutes and stimuli. Old lines are
+/// dropped at most once a day by rewriting the file.
+pub struct History {
+    path: Option<PathBuf>,
+    retention_days: u32,
+    last_pruned_ms: Option<u64>,
+}
+
+impl History {
+    pub fn new(path: Option<PathBuf>, retention_days: u32) -> Self {
+        Self { path, retention_days, last_pruned_ms: None }
+    }
+
+    /// Zero keeps everything.
+    pub fn set_retention_days(&mut self, days: u32) {
+        if days != self.retention_days {
+            self.retention_days = days;
+            self.last_pruned_ms = None;
+        }
+    }
+
+    pub fn append(&mut self, record: &HistoryRecord) {
+        self.append_all(std::slice::from_ref(record));
+    }
+
+    pub fn append_all(&mut self, records: &[HistoryRecord]) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let Some(newest) = records.iter().map(HistoryRecord::at_ms).max() else {
+            return;
+        };
+        if let Some(parent) = path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(path) {
+            for record in records {
+                if let Ok(line) = serde_json::to_string(record) {
+                    let _ = writeln!(file, "{line}");
+                }
+            }
+        }
+        if self.last_pruned_ms.is_none_or(|pruned| newest.saturating_sub(pruned) >= DAY_MS) {
+            self.prune(newest);
+        }
+    }
+
+    /// Records with `from_ms <= at_ms < to_ms`, oldest first. Unparseable lines are skipped.
+    /// The file is read line by line and only lines in range are fully parsed.
+    pub fn query(&self, from_ms: Option<u64>, to_ms: Option<u64>) -> Vec<HistoryRecord> {
+        let range = from_ms.unwrap_or(0)..to_ms.unwrap_or(u64::MAX);
+        self.lines()
+            .filter(|line| line_time(line).is_some_and(|at_ms| range.contains(&at_ms)))
+            .filter_map(|line| serde_json::from_str(&line).ok())
+            .collect()
+    }
+
+    /// Drops records older than the retention window.
+    pub fn prune(&mut self, now_ms: u64) {
+        self.last_pruned_ms = Some(now_ms);
+        if self.retention_days == 0 {
+            return;
+        }
+        let Some(path) = &self.path else {
+            return;
+        };
+        let cutoff = now_ms.saturating_sub(self.retention_days as u64 * DAY_MS);
+        // Write aside and rename so a crash mid-prune can't truncate the log.
+        let staging = path.with_extension("jsonl.tmp");
+        let Ok(file) = fs::File::create(&staging) else {
+            return;
+        };
+        let mut kept = BufWriter::new(file);
+        let mut dropped = false;
+        for line in self.lines() {
+            if line_time(&line).is_some_and(|at_ms| at_ms >= cutoff) {
+                if writeln!(kept, "{line}").is_err() {
+                    let _ = fs::remove_file(&staging);
+                    return;
+                }
+            } else {
+                dropped = true;
+            }
+        }
+        if dropped && kept.flush().is_ok() {
+            let _ = fs::rename(&staging, path);
+        } else {
+            let _ = fs::remove_file(&staging);
+        }
+    }
+
+    fn lines(&self) -> impl Iterator<Item = String> {
+        let file = self.path.as_ref().and_then(|path| fs::File::open(path).ok());
+        file.into_iter().flat_map(|file| BufReader::new(file).lines().map_while(Result::ok))
+    }
+}
+
+/// Just the timestamp of a line, so records can be filtered without parsing them whole.
+fn line_time(line: &str) -> Option<u64> {
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Timestamp {
+        at_ms: u64,
+    }
+    serde_json::from_str::<Timestamp>(line).ok().map(|timestamp| timestamp.at_ms)
+}
+
+pub fn history_file(app: &AppHandle) -> Option<PathBuf> {
+    let mut dir = app.path().app_data_dir().ok()?;
+    dir.push("history.jsonl");
+    Some(dir)
+}
+
+/// Logs a send attempt, if history is set up yet.
+pub fn record_stimulus(
+    app: &AppHandle,
+    stimulus: Stimulus,
+    intensity: Intensity,
+    reason: &str,
+    result: &Result<(), PavlokError>,
+    retry: bool,
+) {
+    let record = HistoryRecord::Stimulus {
+        at_ms: crate::alert_queue::now_ms(),
+        stimulus,
+        intensity,
+        reason: reason.to_string(),
+        error: result.as_ref().err().cloned(),
+        retry,
+    };
+    if let Some(history) = app.try_state::<Mutex<History>>() {
+        history.lock().unwrap().append(&record);
+    }
+}
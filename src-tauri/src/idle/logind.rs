@@ -0,0 +1,44 @@
+use super::IdleSource;
+use std::time::{SystemTime, UNIX_EPOCH};
+use zbus::blocking::{proxy::Builder, Connection, Proxy};
+use zbus::proxy::CacheProperties;
+
+// logind resolves "auto" to the session of the calling process.
+const SESSION_PATH: &str = "/org/freedesktop/login1/session/auto";
+
+/// Coarse fallback: logind only flips `IdleHint` once the desktop environment
+/// decides the session is idle, which is typically after a minute or more.
+pub struct LogindIdle {
+    session: Proxy<'static>,
+}
+
+impl LogindIdle {
+    pub fn connect() -> Option<Self> {
+        let conn = Connection::system().ok()?;
+        let session: Proxy<'static> = Builder::new(&conn)
+            .destination("org.freedesktop.login1")
+            .ok()?
+            .path(SESSION_PATH)
+            .ok()?
+            .interface("org.freedesktop.login1.Session")
+            .ok()?
+            .cache_properties(CacheProperties::No)
+            .build()
+            .ok()?;
+        session.get_property::<bool>("IdleHint").ok()?;
+        Some(Self { session })
+    }
+}
+
+impl IdleSource for LogindIdle {
+    fn idle_seconds(&self) -> Option<f64> {
+        let idle: bool = self.session.get_property("IdleHint").ok()?;
+        if !idle {
+            return Some(0.0);
+        }
+        // IdleSinceHint is a CLOCK_REALTIME timestamp in microseconds.
+        let idle_since_usec: u64 = self.session.get_property("IdleSinceHint").ok()?;
+        let now_usec = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_micros() as u64;
+        Some(now_usec.saturating_sub(idle_since_usec) as f64 / 1_000_000.0)
+    }
+}
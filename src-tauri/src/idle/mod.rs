@@ -0,0 +1,59 @@
+#[cfg(target_os = "linux")]
+mod logind;
+#[cfg(target_os = "linux")]
+mod wayland;
+#[cfg(target_os = "windows")]
+mod win32;
+#[cfg(target_os = "linux")]
+mod x11;
+
+pub trait IdleSource: Send {
+    /// Seconds since the last user input, or `None` when the probe failed.
+    fn idle_seconds(&self) -> Option<f64>;
+}
+
+/// Picks the idle backend for the current session.
+#[cfg(target_os = "windows")]
+pub fn detect() -> Box<dyn IdleSource> {
+    Box::new(win32::LastInputIdle)
+}
+
+/// Picks the idle backend for the current session, most precise first.
+/// XScreenSaver only sees X clients, so it is skipped under Wayland where it
+/// would report XWayland idle time instead of the real session's.
+#[cfg(target_os = "linux")]
+pub fn detect() -> Box<dyn IdleSource> {
+    if is_wayland_session() {
+        if let Some(source) = wayland::WaylandIdle::connect() {
+            return Box::new(source);
+        }
+    } else if let Some(source) = x11::XScreenSaverIdle::connect() {
+        return Box::new(source);
+    }
+    if let Some(source) = logind::LogindIdle::connect() {
+        return Box::new(source);
+    }
+    Box::new(Unavailable)
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+pub fn detect() -> Box<dyn IdleSource> {
+    Box::new(Unavailable)
+}
+
+#[cfg(target_os = "linux")]
+fn is_wayland_session() -> bool {
+    std::env::var_os("WAYLAND_DISPLAY").is_some()
+        || std::env::var("XDG_SESSION_TYPE").is_ok_and(|kind| kind.eq_ignore_ascii_case("wayland"))
+}
+
+// No backend could be reached; every probe fails, which the engine counts as rest.
+#[cfg(not(target_os = "windows"))]
+struct Unavailable;
+
+#[cfg(not(target_os = "windows"))]
+impl IdleSource for Unavailable {
+    fn idle_seconds(&self) -> Option<f64> {
+        None
+    }
+}
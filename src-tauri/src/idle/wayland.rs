@@ -0,0 +1,122 @@
+use super::IdleSource;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use wayland_client::globals::{registry_queue_init, GlobalListContents};
+use wayland_client::protocol::{wl_registry, wl_seat::WlSeat};
+use wayland_client::{Connection, Dispatch, Proxy, QueueHandle};
+use wayland_protocols::ext::idle_notify::v1::client::ext_idle_notification_v1::{self, ExtIdleNotificationV1};
+use wayland_protocols::ext::idle_notify::v1::client::ext_idle_notifier_v1::ExtIdleNotifierV1;
+use wayland_protocols_plasma::idle::client::org_kde_kwin_idle::OrgKdeKwinIdle;
+use wayland_protocols_plasma::idle::client::org_kde_kwin_idle_timeout::{self, OrgKdeKwinIdleTimeout};
+
+// Compositors only tell us when the seat crosses this threshold, so it bounds
+// how late an idle period is noticed. It stays below the engine's 2s "active" cutoff.
+const IDLE_TIMEOUT_MS: u32 = 1000;
+
+/// Turns compositor idled/resumed notifications into an idle-seconds reading.
+/// Prefers ext-idle-notify-v1 and falls back to KWin's org_kde_kwin_idle.
+pub struct WaylandIdle {
+    idle_since: Arc<Mutex<Option<Instant>>>,
+    connected: Arc<AtomicBool>,
+}
+
+struct NotificationState {
+    idle_since: Arc<Mutex<Option<Instant>>>,
+}
+
+impl NotificationState {
+    fn idled(&self) {
+        let started = Instant::now() - Duration::from_millis(IDLE_TIMEOUT_MS as u64);
+        *self.idle_since.lock().unwrap() = Some(started);
+    }
+
+    fn resumed(&self) {
+        *self.idle_since.lock().unwrap() = None;
+    }
+}
+
+impl WaylandIdle {
+    pub fn connect() -> Option<Self> {
+        let conn = Connection::connect_to_env().ok()?;
+        let (globals, mut queue) = registry_queue_init::<NotificationState>(&conn).ok()?;
+        let qh = queue.handle();
+        let seat: WlSeat = globals.bind(&qh, 1..=1, ()).ok()?;
+
+        if let Ok(notifier) = globals.bind::<ExtIdleNotifierV1, _, _>(&qh, 1..=2, ()) {
+            // v2 can ignore idle inhibitors (e.g. video playback), which is what we want:
+            // only real input should count as activity.
+            if notifier.version() >= 2 {
+                notifier.get_input_idle_notification(IDLE_TIMEOUT_MS, &seat, &qh, ());
+            } else {
+                notifier.get_idle_notification(IDLE_TIMEOUT_MS, &seat, &qh, ());
+            }
+        } else if let Ok(kde_idle) = globals.bind::<OrgKdeKwinIdle, _, _>(&qh, 1..=1, ()) {
+            kde_idle.get_idle_timeout(&seat, IDLE_TIMEOUT_MS, &qh, ());
+        } else {
+            return None;
+        }
+
+        let idle_since = Arc::new(Mutex::new(None));
+        let mut state = NotificationState { idle_since: idle_since.clone() };
+        queue.roundtrip(&mut state).ok()?;
+
+        let connected = Arc::new(AtomicBool::new(true));
+        let connected_flag = connected.clone();
+        thread::spawn(move || {
+            // Keep the seat alive for as long as the notification object needs it.
+            let _seat = seat;
+            while queue.blocking_dispatch(&mut state).is_ok() {}
+            connected_flag.store(false, Ordering::Relaxed);
+        });
+
+        Some(Self { idle_since, connected })
+    }
+}
+
+impl IdleSource for WaylandIdle {
+    fn idle_seconds(&self) -> Option<f64> {
+        if !self.connected.load(Ordering::Relaxed) {
+            return None;
+        }
+        let idle_since = *self.idle_since.lock().unwrap();
+        Some(idle_since.map_or(0.0, |since| since.elapsed().as_secs_f64()))
+    }
+}
+
+impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for NotificationState {
+    fn event(_: &mut Self, _: &wl_registry::WlRegistry, _: wl_registry::Event, _: &GlobalListContents, _: &Connection, _: &QueueHandle<Self>) {}
+}
+
+impl Dispatch<WlSeat, ()> for NotificationState {
+    fn event(_: &mut Self, _: &WlSeat, _: <WlSeat as Proxy>::Event, _: &(), _: &Connection, _: &QueueHandle<Self>) {}
+}
+
+impl Dispatch<ExtIdleNotifierV1, ()> for NotificationState {
+    fn event(_: &mut Self, _: &ExtIdleNotifierV1, _: <ExtIdleNotifierV1 as Proxy>::Event, _: &(), _: &Connection, _: &QueueHandle<Self>) {}
+}
+
+impl Dispatch<ExtIdleNotificationV1, ()> for NotificationState {
+    fn event(state: &mut Self, _: &ExtIdleNotificationV1, event: ext_idle_notification_v1::Event, _: &(), _: &Connection, _: &QueueHandle<Self>) {
+        match event {
+            ext_idle_notification_v1::Event::Idled => state.idled(),
+            ext_idle_notification_v1::Event::Resumed => state.resumed(),
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<OrgKdeKwinIdle, ()> for NotificationState {
+    fn event(_: &mut Self, _: &OrgKdeKwinIdle, _: <OrgKdeKwinIdle as Proxy>::Event, _: &(), _: &Connection, _: &QueueHandle<Self>) {}
+}
+
+impl Dispatch<OrgKdeKwinIdleTimeout, ()> for NotificationState {
+    fn event(state: &mut Self, _: &OrgKdeKwinIdleTimeout, event: org_kde_kwin_idle_timeout::Event, _: &(), _: &Connection, _: &QueueHandle<Self>) {
+        match event {
+            org_kde_kwin_idle_timeout::Event::Idle => state.idled(),
+            org_kde_kwin_idle_timeout::Event::Resumed => state.resumed(),
+            _ => {}
+        }
+    }
+}
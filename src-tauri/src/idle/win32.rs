@@ -0,0 +1,20 @@
+use super::IdleSource;
+use winapi::um::sysinfoapi::GetTickCount;
+use winapi::um::winuser::{GetLastInputInfo, LASTINPUTINFO};
+
+pub struct LastInputIdle;
+
+impl IdleSource for LastInputIdle {
+    fn idle_seconds(&self) -> Option<f64> {
+        unsafe {
+            let mut info: LASTINPUTINFO = std::mem::zeroed();
+            info.cbSize = std::mem::size_of::<LASTINPUTINFO>() as u32;
+            if GetLastInputInfo(&mut info) != 0 {
+                let ticks = GetTickCount();
+                let idle_ticks = ticks.wrapping_sub(info.dwTime);
+                return Some((idle_ticks as f64) / 1000.0);
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,28 @@
+use super::IdleSource;
+use x11rb::connection::Connection;
+use x11rb::protocol::screensaver::ConnectionExt as _;
+use x11rb::protocol::xproto::Window;
+use x11rb::rust_connection::RustConnection;
+
+pub struct XScreenSaverIdle {
+    conn: RustConnection,
+    root: Window,
+}
+
+impl XScreenSaverIdle {
+    pub fn connect() -> Option<Self> {
+        std::env::var_os("DISPLAY")?;
+        let (conn, screen_num) = x11rb::connect(None).ok()?;
+        let root = conn.setup().roots.get(screen_num)?.root;
+        // Fails when the server lacks the MIT-SCREEN-SAVER extension.
+        conn.screensaver_query_version(1, 1).ok()?.reply().ok()?;
+        Some(Self { conn, root })
+    }
+}
+
+impl IdleSource for XScreenSaverIdle {
+    fn idle_seconds(&self) -> Option<f64> {
+        let info = self.conn.screensaver_query_info(self.root).ok()?.reply().ok()?;
+        Some(info.ms_since_user_input as f64 / 1000.0)
+    }
+}
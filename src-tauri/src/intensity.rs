@@ -0,0 +1,109 @@
+use crate::pavlok::{Intensity, Stimulus};
+use crate::settings::{self, SettingsPatch};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use tauri::menu::{CheckMenuItem, Submenu};
+use tauri::{AppHandle, Manager, Wry};
+
+const TRAY_LEVELS: [u32; 5] = [10, 25, 50, 75, 100];
+const MENU_ID_PREFIX: &str = "intensity:";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Intensities {
+    #[serde(default = "default_beep")]
+    pub beep: Intensity,
+    #[serde(default = "default_vibe")]
+    pub vibe: Intensity,
+    #[serde(default = "default_zap")]
+    pub zap: Intensity,
+}
+
+fn default_beep() -> Intensity {
+    Stimulus::Beep.default_intensity()
+}
+
+fn default_vibe() -> Intensity {
+    Stimulus::Vibe.default_intensity()
+}
+
+fn default_zap() -> Intensity {
+    Stimulus::Zap.default_intensity()
+}
+
+impl Default for Intensities {
+    fn default() -> Self {
+        Self { beep: default_beep(), vibe: default_vibe(), zap: default_zap() }
+    }
+}
+
+impl Intensities {
+    pub fn get(&self, stimulus: Stimulus) -> Intensity {
+        match stimulus {
+            Stimulus::Beep => self.beep,
+            Stimulus::Vibe => self.vibe,
+            Stimulus::Zap => self.zap,
+        }
+    }
+
+    pub fn set(&mut self, stimulus: Stimulus, intensity: Intensity) {
+        match stimulus {
+            Stimulus::Beep => self.beep = intensity,
+            Stimulus::Vibe => self.vibe = intensity,
+            Stimulus::Zap => self.zap = intensity,
+        }
+    }
+}
+
+/// Stores a new level through the settings file, which also updates the tray and the widget.
+pub fn update(app: &AppHandle, stimulus: Stimulus, intensity: Intensity) -> Result<Intensities, String> {
+    let mut intensities = *app.state::<Mutex<Intensities>>().lock().unwrap();
+    intensities.set(stimulus, intensity);
+    let patch = SettingsPatch { intensity: Some(intensities), ..SettingsPatch::default() };
+    settings::update(app, patch).map(|settings| settings.intensity)
+}
+
+/// Tray submenu with one radio-style level list per stimulus type.
+pub struct IntensityMenu {
+    submenu: Submenu<Wry>,
+    items: Vec<(Stimulus, Intensity, CheckMenuItem<Wry>)>,
+}
+
+impl IntensityMenu {
+    pub fn build<M: Manager<Wry>>(manager: &M, current: &Intensities) -> tauri::Result<Self> {
+        let submenu = Submenu::new(manager, "Intensity", true)?;
+        let mut items = Vec::new();
+
+        for (stimulus, label) in [(Stimulus::Beep, "Beep"), (Stimulus::Vibe, "Vibe"), (Stimulus::Zap, "Zap")] {
+            let levels = Submenu::new(manager, label, true)?;
+            for level in TRAY_LEVELS {
+                let intensity = Intensity::new(level).expect("tray levels are within range");
+                let id = format!("{MENU_ID_PREFIX}{}:{level}", stimulus.as_str());
+                let checked = current.get(stimulus) == intensity;
+                let item = CheckMenuItem::with_id(manager, id, format!("{level}%"), true, checked, None::<&str>)?;
+                levels.append(&item)?;
+                items.push((stimulus, intensity, item));
+            }
+            submenu.append(&levels)?;
+        }
+
+        Ok(Self { submenu, items })
+    }
+
+    pub fn submenu(&self) -> &Submenu<Wry> {
+        &self.submenu
+    }
+
+    pub fn sync(&self, current: &Intensities) {
+        for (stimulus, intensity, item) in &self.items {
+            let _ = item.set_checked(current.get(*stimulus) == *intensity);
+        }
+    }
+
+    /// Parses ids like `intensity:zap:25` produced by [`IntensityMenu::build`].
+    pub fn parse_id(id: &str) -> Option<(Stimulus, Intensity)> {
+        let (stimulus, level) = id.strip_prefix(MENU_ID_PREFIX)?.split_once(':')?;
+        let intensity = Intensity::new(level.parse().ok()?).ok()?;
+        Some((Stimulus::from_mode(stimulus), intensity))
+    }
+}
@@ -1,14 +1,17 @@
-use tauri::{Emitter, LogicalSize, Manager, PhysicalPosition, PhysicalSize, Position, Size, WebviewWindow, Window, window::Color};
+use tauri::{Emitter, LogicalSize, Manager, PhysicalPosition, PhysicalSize, Position, Size, WebviewWindow, Window};
 use tauri::tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent};
-use tauri::menu::{Menu, MenuItem};
+use tauri::menu::{Menu, MenuItem, PredefinedMenuItem};
+use tauri_plugin_notification::NotificationExt;
 use tauri_plugin_opener::OpenerExt;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
-use std::time::Duration;
-use winapi::um::winuser::{GetLastInputInfo, LASTINPUTINFO};
-use winapi::um::sysinfoapi::GetTickCount;
+use std::sync::Mutex;
 
+#[cfg(target_os = "windows")]
+use std::time::Duration;
+#[cfg(target_os = "windows")]
+use tauri::window::Color;
 #[cfg(target_os = "windows")]
 use raw_window_handle::{HasWindowHandle, RawWindowHandle};
 #[cfg(target_os = "windows")]
@@ -18,6 +21,45 @@ use windows::Win32::UI::WindowsAndMessaging::SendMessageW;
 #[cfg(target_os = "windows")]
 use windows::Win32::UI::Input::KeyboardAndMouse::ReleaseCapture;
 
+pub mod alert_queue;
+pub mod cli;
+pub mod escalation;
+pub mod export;
+pub mod fatigue;
+pub mod history;
+pub mod idle;
+pub mod intensity;
+pub mod local_api;
+pub mod metrics;
+mod monitor;
+pub mod mqtt;
+pub mod notifier;
+pub mod pavlok;
+pub mod settings;
+pub mod snooze;
+pub mod stats;
+pub mod token_store;
+pub mod tray_controls;
+pub mod tray_status;
+pub mod webhooks;
+
+use alert_queue::{RetryPolicy, StimulusQueue};
+use escalation::{Escalation, EscalationPolicy, EscalationToggle};
+use export::ExportFormat;
+use fatigue::{FatigueEngine, FatigueSnapshot, SystemClock};
+use history::{History, HistoryRecord};
+use intensity::{Intensities, IntensityMenu};
+use local_api::LocalApi;
+use metrics::Metrics;
+use mqtt::Mqtt;
+use pavlok::{AccountInfo, Intensity, PavlokClient, PavlokError, Stimulus};
+use settings::{LegacySettings, Settings, SettingsPatch, SettingsStore};
+use snooze::{Snooze, SnoozeDuration, SnoozeState};
+use stats::{Stats, StatsRange};
+use token_store::{TokenStorage, TokenStore};
+use tray_controls::{TrayAction, TrayControls};
+use tray_status::TrayStatus;
+
 #[derive(Debug, Serialize, Deserialize)]
 struct SavedWindowState {
     x: i32,
@@ -41,7 +83,7 @@ fn window_state_file(app: &tauri::AppHandle) -> Option<PathBuf> {
 }
 
 fn save_window_state(window: &Window) {
-    let Some(path) = window_state_file(&window.app_handle()) else {
+    let Some(path) = window_state_file(window.app_handle()) else {
         return;
     };
 
@@ -65,7 +107,7 @@ fn save_window_state(window: &Window) {
 }
 
 fn save_webview_window_state(window: &WebviewWindow) {
-    let Some(path) = window_state_file(&window.app_handle()) else {
+    let Some(path) = window_state_file(window.app_handle()) else {
         return;
     };
 
@@ -89,7 +131,7 @@ fn save_webview_window_state(window: &WebviewWindow) {
 }
 
 fn restore_window_state(window: &WebviewWindow) -> bool {
-    let Some(path) = window_state_file(&window.app_handle()) else {
+    let Some(path) = window_state_file(window.app_handle()) else {
         return false;
     };
 
@@ -140,61 +182,205 @@ fn clear_saved_window_state(app: &tauri::AppHandle) {
 
 
 #[tauri::command]
-fn get_idle_seconds() -> f64 {
-    unsafe {
-        let mut info: LASTINPUTINFO = std::mem::zeroed();
-        info.cbSize = std::mem::size_of::<LASTINPUTINFO>() as u32;
-        if GetLastInputInfo(&mut info) != 0 {
-            let ticks = GetTickCount();
-            let idle_ticks = ticks - info.dwTime;
-            return (idle_ticks as f64) / 1000.0;
-        }
+fn get_idle_seconds(engine: tauri::State<'_, Mutex<FatigueEngine>>) -> f64 {
+    engine.lock().unwrap().probe_idle().unwrap_or(0.0)
+}
+
+#[tauri::command]
+fn get_fatigue_state(engine: tauri::State<'_, Mutex<FatigueEngine>>) -> FatigueSnapshot {
+    engine.lock().unwrap().snapshot()
+}
+
+/// History records with `from_ms <= atMs < to_ms`; either bound may be omitted.
+#[tauri::command]
+fn query_history(history: tauri::State<'_, Mutex<History>>, from_ms: Option<u64>, to_ms: Option<u64>) -> Vec<HistoryRecord> {
+    history.lock().unwrap().query(from_ms, to_ms)
+}
+
+#[tauri::command]
+fn get_stats(history: tauri::State<'_, Mutex<History>>, range: StatsRange) -> Stats {
+    let records = history.lock().unwrap().query(Some(range.from_ms), Some(range.to_ms));
+    stats::compute(range, &records)
+}
+
+/// Writes history in `range` (all of it when omitted) to `path`, an absolute
+/// path ending in `.csv` or `.jsonl` to match `format`; returns the number of records.
+#[tauri::command]
+fn export_history(
+    history: tauri::State<'_, Mutex<History>>,
+    format: ExportFormat,
+    range: Option<StatsRange>,
+    path: PathBuf,
+) -> Result<usize, String> {
+    export::check_target(&path, format)?;
+    export::export(&history, format, range, &path).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn set_monitoring(app: tauri::AppHandle, enabled: bool) -> FatigueSnapshot {
+    monitor::set_monitoring(&app, enabled)
+}
+
+#[tauri::command]
+fn set_fatigue_limits(
+    app: tauri::AppHandle,
+    engine: tauri::State<'_, Mutex<FatigueEngine>>,
+    work_minutes: u32,
+    break_minutes: u32,
+) -> Result<FatigueSnapshot, String> {
+    let patch = SettingsPatch { work_minutes: Some(work_minutes), break_minutes: Some(break_minutes), ..SettingsPatch::default() };
+    settings::update(&app, patch)?;
+    let state = engine.lock().unwrap().snapshot();
+    Ok(state)
+}
+
+#[tauri::command]
+fn get_settings(store: tauri::State<'_, Mutex<SettingsStore>>) -> Settings {
+    store.lock().unwrap().get().clone()
+}
+
+/// The local API token, for the widget's explicit "copy" button; settings.toml
+/// and `get_settings` only show `hasToken`.
+#[tauri::command]
+fn reveal_local_api_token(store: tauri::State<'_, Mutex<SettingsStore>>) -> Result<String, String> {
+    let local_api = store.lock().unwrap().get().local_api.clone();
+    if !local_api.enabled || local_api.token.is_empty() {
+        return Err("The local API is off; enable it under [localApi] in settings.toml".to_string());
     }
-    0.0
+    Ok(local_api.token)
 }
 
 #[tauri::command]
-fn send_pavlok_alert(token: String, stimulus_type: String) -> String {
-    let client = reqwest::blocking::Client::new();
-    let url = "https://api.pavlok.com/api/v5/stimulus/send";
-    let token_trimmed = token.trim();
-    let token_value = {
-        let mut parts = token_trimmed.split_whitespace();
-        match (parts.next(), parts.next()) {
-            (Some(scheme), Some(value)) if scheme.eq_ignore_ascii_case("bearer") => value,
-            _ => token_trimmed,
-        }
-    };
-    
-    let actual_type = match stimulus_type.as_str() {
-        "vibro" => "vibe",
-        "vibration" => "vibe",
-        "vibe" => "vibe",
-        "zap" => "zap",
-        _ => "beep",
-    };
+fn update_settings(app: tauri::AppHandle, patch: SettingsPatch) -> Result<Settings, String> {
+    settings::update(&app, patch)
+}
 
-    let payload = serde_json::json!({
-        "stimulus": {
-            "stimulusType": actual_type,
-            "stimulusValue": 100
-        },
-        "reason": "Fatigue limit"
-    });
-
-    match client.post(url)
-        .header("Authorization", format!("Bearer {}", token_value))
-        .json(&payload)
-        .send() {
-            Ok(res) => {
-                if res.status().is_success() {
-                    return "Sent".to_string();
-                } else {
-                    return format!("Error: {}", res.status());
-                }
-            },
-            Err(e) => return format!("Failed: {}", e),
-        }
+#[tauri::command]
+fn import_legacy_settings(
+    app: tauri::AppHandle,
+    tokens: tauri::State<'_, TokenStore>,
+    queue: tauri::State<'_, Mutex<StimulusQueue>>,
+    legacy: LegacySettings,
+) -> Result<Settings, String> {
+    if let Some(token) = legacy.api_token.as_deref().filter(|token| !token.trim().is_empty()) {
+        tokens.save(token)?;
+        queue.lock().unwrap().set_token(&tokens.token().unwrap_or_default());
+    }
+
+    settings::import_legacy(&app, &legacy)
+}
+
+#[tauri::command]
+fn reset_fatigue(app: tauri::AppHandle) {
+    monitor::reset(&app);
+}
+
+/// Without an explicit `intensity` the level saved for that stimulus type is used.
+/// Does nothing while alerts are snoozed.
+#[tauri::command]
+async fn send_pavlok_alert(
+    app: tauri::AppHandle,
+    intensities: tauri::State<'_, Mutex<Intensities>>,
+    stimulus_type: String,
+    intensity: Option<Intensity>,
+) -> Result<(), PavlokError> {
+    if snooze::is_snoozed(&app) {
+        return Ok(());
+    }
+    let stimulus = Stimulus::from_mode(&stimulus_type);
+    let intensity = intensity.unwrap_or_else(|| intensities.lock().unwrap().get(stimulus));
+    tauri::async_runtime::spawn_blocking(move || notifier::send_pavlok(&app, stimulus, intensity, notifier::MANUAL_REASON))
+        .await
+        .unwrap_or_else(|e| Err(PavlokError::Network { message: e.to_string() }))
+}
+
+#[tauri::command]
+fn snooze_alerts(app: tauri::AppHandle, duration: SnoozeDuration) -> Result<SnoozeState, String> {
+    snooze::start(&app, duration)
+}
+
+#[tauri::command]
+fn resume_alerts(app: tauri::AppHandle) -> SnoozeState {
+    snooze::resume(&app)
+}
+
+#[tauri::command]
+fn get_snooze(snooze: tauri::State<'_, Mutex<Snooze>>) -> SnoozeState {
+    let mut snooze = snooze.lock().unwrap();
+    snooze.expire(alert_queue::now_ms());
+    snooze.state()
+}
+
+/// Checks a token against the account endpoint so a bad key shows up right away,
+/// not on the first alert. Without `token` the saved one is checked.
+/// Runs off the main thread: it is called at startup.
+#[tauri::command]
+async fn validate_pavlok_token(
+    pavlok: tauri::State<'_, PavlokClient>,
+    tokens: tauri::State<'_, TokenStore>,
+    token: Option<String>,
+) -> Result<AccountInfo, PavlokError> {
+    let token = token.or_else(|| tokens.token()).ok_or(PavlokError::InvalidToken)?;
+    let pavlok = pavlok.inner().clone();
+    tauri::async_runtime::spawn_blocking(move || pavlok.fetch_account(&token))
+        .await
+        .unwrap_or_else(|e| Err(PavlokError::Network { message: e.to_string() }))
+}
+
+#[tauri::command]
+fn get_escalation_policy(escalation: tauri::State<'_, Mutex<Escalation>>) -> EscalationPolicy {
+    escalation.lock().unwrap().policy().clone()
+}
+
+#[tauri::command]
+fn set_escalation_policy(app: tauri::AppHandle, policy: EscalationPolicy) -> Result<EscalationPolicy, String> {
+    escalation::update(&app, policy)
+}
+
+#[tauri::command]
+fn get_stimulus_intensities(intensities: tauri::State<'_, Mutex<Intensities>>) -> Intensities {
+    *intensities.lock().unwrap()
+}
+
+#[tauri::command]
+fn set_stimulus_intensity(app: tauri::AppHandle, stimulus_type: String, intensity: Intensity) -> Result<Intensities, String> {
+    intensity::update(&app, Stimulus::from_mode(&stimulus_type), intensity)
+}
+
+/// Stores the token in the OS keyring (or the encrypted fallback file).
+/// The widget clears its field afterwards; the token never comes back out.
+#[tauri::command]
+fn save_api_token(
+    tokens: tauri::State<'_, TokenStore>,
+    queue: tauri::State<'_, Mutex<StimulusQueue>>,
+    token: String,
+) -> Result<TokenStorage, String> {
+    let storage = tokens.save(&token)?;
+    queue.lock().unwrap().set_token(&tokens.token().unwrap_or_default());
+    Ok(storage)
+}
+
+/// Reloads the saved token into the backend and reports whether there is one.
+#[tauri::command]
+fn load_api_token(tokens: tauri::State<'_, TokenStore>, queue: tauri::State<'_, Mutex<StimulusQueue>>) -> bool {
+    let found = tokens.load();
+    // Lets queued retries resume after a restart without waiting for the next alert.
+    queue.lock().unwrap().set_token(&tokens.token().unwrap_or_default());
+    found
+}
+
+#[tauri::command]
+fn clear_api_token(app: tauri::AppHandle) -> Result<(), String> {
+    forget_api_token(&app)
+}
+
+const API_TOKEN_CHANGED_EVENT: &str = "api-token-changed";
+
+fn forget_api_token(app: &tauri::AppHandle) -> Result<(), String> {
+    app.state::<Mutex<StimulusQueue>>().lock().unwrap().set_token("");
+    let result = app.state::<TokenStore>().clear();
+    let _ = app.emit(API_TOKEN_CHANGED_EVENT, serde_json::json!({ "saved": false }));
+    result
 }
 
 #[tauri::command]
@@ -211,6 +397,10 @@ fn start_drag(window: tauri::WebviewWindow) -> Result<(), String> {
             }
         }
     }
+    #[cfg(not(target_os = "windows"))]
+    {
+        let _ = window.start_dragging();
+    }
     Ok(())
 }
 
@@ -243,6 +433,9 @@ fn start_resize_drag(window: Window, direction: String) -> Result<(), String> {
             }
         }
     }
+    // The widget is fixed-size elsewhere; resize drag is only wired for Win32.
+    #[cfg(not(target_os = "windows"))]
+    let _ = (window, direction);
     Ok(())
 }
 
@@ -546,9 +739,28 @@ unsafe extern "system" fn enum_child_proc(hwnd: isize, _lparam: isize) -> i32 {
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    // Control flags (`--start`, `--status --json`, ...) are answered by the running instance.
+    let args: Vec<String> = std::env::args().collect();
+    match cli::parse(args.iter().cloned()) {
+        Ok(Some(request)) if request.reply_to.is_none() => std::process::exit(cli::run_client(&args, request.json)),
+        Err(message) => {
+            cli::attach_console();
+            eprintln!("{message}");
+            std::process::exit(2);
+        }
+        _ => {}
+    }
+
     tauri::Builder::default()
+        .manage(Mutex::new(FatigueEngine::new(Box::new(SystemClock), idle::detect())))
+        .manage(PavlokClient::from_env())
         .plugin(tauri_plugin_opener::init())
-        .plugin(tauri_plugin_single_instance::init(|app, _argv, _cwd| {
+        .plugin(tauri_plugin_notification::init())
+        .plugin(tauri_plugin_single_instance::init(|app, argv, _cwd| {
+            if let Ok(Some(request)) = cli::parse(argv) {
+                cli::handle(app, request);
+                return;
+            }
             if let Some(window) = app.get_webview_window("main") {
                 let _ = window.show();
                 #[cfg(target_os = "windows")]
@@ -558,15 +770,61 @@ pub fn run() {
         .setup(|app| {
             const API_KEY_HELP_URL: &str = "https://pavlok.readme.io/reference/intro/authentication";
 
+            let secrets = TokenStore::named(app.handle(), settings::SECRETS_NAME, settings::SECRETS_NAME);
+            let settings_store = SettingsStore::load(settings::settings_file(app.handle()), Some(secrets));
+            for error in settings_store.load_errors() {
+                let _ = app.notification().builder().title("Settings problem").body(error).show();
+            }
+            let settings = settings_store.get().clone();
+            app.manage(Mutex::new(settings_store));
+            app.manage(Mutex::new(Snooze::load(snooze::snooze_file(app.handle()))));
+            app.manage(Mutex::new(History::new(history::history_file(app.handle()), settings.history_retention_days)));
+            app.manage(Mutex::new(Metrics::default()));
+            app.manage(webhooks::spawn_sender());
+
+            let intensity_menu = IntensityMenu::build(app, &settings.intensity)?;
+            app.manage(Mutex::new(settings.intensity));
+
+            let escalation_toggle = EscalationToggle::build(app, &settings.escalation)?;
+            app.manage(Mutex::new(Escalation::new(settings.escalation.clone())));
+
+            let monitoring = app.state::<Mutex<FatigueEngine>>().lock().unwrap().snapshot().monitoring;
+            let tray_controls = TrayControls::build(app, &settings, monitoring)?;
+            settings::apply(app.handle(), &settings);
+
             let get_api_key_i = MenuItem::with_id(app, "get_api_key", "Get API Key", true, None::<&str>)?;
+            let forget_api_key_i = MenuItem::with_id(app, "forget_api_key", "Forget API Key", true, None::<&str>)?;
+            let export_history_i = MenuItem::with_id(app, export::MENU_ID, "Export History (CSV)", true, None::<&str>)?;
             let reset_fatigue_i = MenuItem::with_id(app, "reset_fatigue", "Reset Fatigue", true, None::<&str>)?;
             let reset_default_position_i = MenuItem::with_id(app, "reset_default_position", "Reset Default Position", true, None::<&str>)?;
+            let snooze_menu = snooze::build_menu(app)?;
+            let controls_separator_i = PredefinedMenuItem::separator(app)?;
+            let separator_i = PredefinedMenuItem::separator(app)?;
             let quit_i = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?;
-            let menu = Menu::with_items(app, &[&get_api_key_i, &reset_fatigue_i, &reset_default_position_i, &quit_i])?;
+            let menu = Menu::with_items(app, &[
+                tray_controls.monitoring_item(),
+                tray_controls.alert_mode_submenu(),
+                tray_controls.presets_submenu(),
+                &snooze_menu,
+                &controls_separator_i,
+                &get_api_key_i,
+                &forget_api_key_i,
+                intensity_menu.submenu(),
+                &escalation_toggle.0,
+                &export_history_i,
+                &reset_fatigue_i,
+                &reset_default_position_i,
+                &separator_i,
+                &quit_i,
+            ])?;
+            app.manage(intensity_menu);
+            app.manage(escalation_toggle);
+            app.manage(tray_controls);
 
             let tray_icon = app.default_window_icon().cloned();
 
-            let mut tray_builder = TrayIconBuilder::new()
+            app.manage(Mutex::new(TrayStatus::default()));
+            let mut tray_builder = TrayIconBuilder::with_id(tray_status::TRAY_ID)
                 .show_menu_on_left_click(false)
                 .menu(&menu)
                 .on_menu_event(|app, event| {
@@ -574,8 +832,10 @@ pub fn run() {
                         app.exit(0);
                     } else if event.id() == "get_api_key" {
                         let _ = app.opener().open_url(API_KEY_HELP_URL, None::<&str>);
+                    } else if event.id() == "forget_api_key" {
+                        let _ = forget_api_token(app);
                     } else if event.id() == "reset_fatigue" {
-                        let _ = app.emit("reset-fatigue", ());
+                        monitor::reset(app);
                     } else if event.id() == "reset_default_position" {
                         clear_saved_window_state(app);
                         if let Some(window) = app.get_webview_window("main") {
@@ -584,6 +844,16 @@ pub fn run() {
                             let _ = ensure_webview_borderless(&window);
                             save_webview_window_state(&window);
                         }
+                    } else if event.id() == export::MENU_ID {
+                        export::export_from_tray(app);
+                    } else if event.id() == escalation::MENU_ID {
+                        escalation::toggle(app);
+                    } else if let Some((stimulus, level)) = IntensityMenu::parse_id(event.id().as_ref()) {
+                        let _ = intensity::update(app, stimulus, level);
+                    } else if let Some(action) = TrayAction::parse_id(event.id().as_ref()) {
+                        tray_controls::handle(app, action);
+                    } else if let Some(action) = snooze::parse_menu_id(event.id().as_ref()) {
+                        snooze::handle_menu(app, action);
                     }
                 })
                 .on_tray_icon_event(|tray, event| {
@@ -607,13 +877,35 @@ pub fn run() {
 
             let _tray = tray_builder.build(app)?;
 
-            // Apply borderless fix on startup
-            #[cfg(target_os = "windows")]
+            let tokens = TokenStore::for_app(app.handle());
+            tokens.load();
+            let mut queue = StimulusQueue::load(alert_queue::queue_file(app.handle()), RetryPolicy::from_env());
+            queue.set_token(&tokens.token().unwrap_or_default());
+            app.manage(tokens);
+            app.manage(Mutex::new(queue));
+            alert_queue::spawn_worker(app.handle().clone());
+            monitor::restore_state(app.handle());
+            let state = app.state::<Mutex<FatigueEngine>>().lock().unwrap().snapshot();
+            tray_status::update(app.handle(), &state);
+            monitor::spawn_sampler(app.handle().clone());
+            // Started last so requests only ever see a fully set up app.
+            app.manage(LocalApi::default());
+            local_api::configure(app.handle(), &settings.local_api);
+            app.manage(Mqtt::default());
+            mqtt::configure(app.handle(), &settings.mqtt);
+
+            // Launched by the command line with no instance running: this copy is the app now.
+            if let Ok(Some(request)) = cli::parse(std::env::args()) {
+                cli::handle(app.handle(), request);
+            }
+
+            // Restore position and apply borderless fix on startup
             if let Some(window) = app.get_webview_window("main") {
                 let _ = window.set_min_size(Some(Size::Logical(LogicalSize::new(MIN_WIDGET_WIDTH, MIN_WIDGET_HEIGHT))));
                 if !restore_window_state(&window) {
                     reset_window_to_default(&window);
                 }
+                #[cfg(target_os = "windows")]
                 let _ = ensure_webview_borderless(&window);
             }
 
@@ -642,7 +934,40 @@ pub fn run() {
                 _ => {}
             }
         })
-        .invoke_handler(tauri::generate_handler![get_idle_seconds, send_pavlok_alert, start_drag, start_resize_drag])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .invoke_handler(tauri::generate_handler![
+            get_idle_seconds,
+            get_fatigue_state,
+            query_history,
+            get_stats,
+            export_history,
+            set_monitoring,
+            set_fatigue_limits,
+            get_settings,
+            update_settings,
+            reveal_local_api_token,
+            import_legacy_settings,
+            reset_fatigue,
+            send_pavlok_alert,
+            snooze_alerts,
+            resume_alerts,
+            get_snooze,
+            save_api_token,
+            load_api_token,
+            clear_api_token,
+            validate_pavlok_token,
+            get_stimulus_intensities,
+            set_stimulus_intensity,
+            get_escalation_policy,
+            set_escalation_policy,
+            start_drag,
+            start_resize_drag
+        ])
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|app, event| {
+            // Covers the tray Quit item as well as OS logoff/shutdown.
+            if let tauri::RunEvent::Exit = event {
+                monitor::save_state(app);
+            }
+        });
 }
@@ -0,0 +1,295 @@
+use crate::cli::{self, CliCommand, CliRequest, StatusReport};
+use crate::fatigue::{EngineEvent, FatigueEngine, FatigueUpdate};
+use crate::metrics::{self, Metrics};
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use std::net::Ipv4Addr;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tiny_http::{Header, Request, Response, Server};
+
+pub const DEFAULT_PORT: u16 = 27_182;
+pub const MIN_TOKEN_LENGTH: usize = 16;
+// Comment lines keep idle streams open through proxies and reveal closed clients.
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+const MAX_BODY_BYTES: u64 = 64 * 1024;
+
+/// The opt-in HTTP API, stored under `[localApi]` in settings.toml.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LocalApiSettings {
+    pub enabled: bool,
+    pub port: u16,
+    /// Sent as `Authorization: Bearer <token>` or `?token=<token>`. Kept in the
+    /// keyring; settings.toml and `get_settings` only show `hasToken`.
+    #[serde(rename(serialize = "hasToken"), serialize_with = "crate::settings::serialize_is_set")]
+    pub token: String,
+    /// Also serve Prometheus metrics on `/metrics`.
+    pub metrics: bool,
+}
+
+impl Default for LocalApiSettings {
+    fn default() -> Self {
+        Self { enabled: false, port: DEFAULT_PORT, token: String::new(), metrics: false }
+    }
+}
+
+impl LocalApiSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.port < 1024 {
+            return Err(format!("localApi.port must be between 1024 and 65535, got {}", self.port));
+        }
+        if self.enabled && self.token.len() < MIN_TOKEN_LENGTH {
+            return Err(format!("localApi.token must be at least {MIN_TOKEN_LENGTH} characters"));
+        }
+        Ok(())
+    }
+
+    /// Enabling the API without a token generates one.
+    pub fn ensure_token(&mut self) {
+        if self.enabled && self.token.is_empty() {
+            self.token = uuid::Uuid::new_v4().simple().to_string();
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Route {
+    Status,
+    Events,
+    Metrics,
+    Command(CliCommand),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiError {
+    pub status: u16,
+    pub message: String,
+}
+
+impl ApiError {
+    fn new(status: u16, message: impl Into<String>) -> Self {
+        Self { status, message: message.into() }
+    }
+}
+
+#[derive(Deserialize)]
+struct SnoozeBody {
+    minutes: u32,
+}
+
+/// Maps a request to what it asks for. `/snooze` takes its minutes from the
+/// query (`?minutes=30`) or a JSON body (`{"minutes": 30}`).
+pub fn route(method: &str, url: &str, body: &str) -> Result<Route, ApiError> {
+    let path = url.split('?').next().unwrap_or_default().trim_end_matches('/');
+    let (expected, route) = match path {
+        "/status" => ("GET", Route::Status),
+        "/events" => ("GET", Route::Events),
+        "/metrics" => ("GET", Route::Metrics),
+        "/start" => ("POST", Route::Command(CliCommand::Start)),
+        "/stop" => ("POST", Route::Command(CliCommand::Stop)),
+        "/reset" => ("POST", Route::Command(CliCommand::Reset)),
+        "/snooze" => {
+            let minutes = query_param(url, "minutes")
+                .and_then(|value| value.parse().ok())
+                .or_else(|| serde_json::from_str::<SnoozeBody>(body).ok().map(|body| body.minutes))
+                .ok_or_else(|| ApiError::new(400, "snooze needs a number of minutes"))?;
+            ("POST", Route::Command(CliCommand::Snooze { minutes }))
+        }
+        _ => return Err(ApiError::new(404, "not found")),
+    };
+    if !method.eq_ignore_ascii_case(expected) {
+        return Err(ApiError::new(405, format!("use {expected}")));
+    }
+    Ok(route)
+}
+
+fn query_param<'a>(url: &'a str, name: &str) -> Option<&'a str> {
+    url.split_once('?')?
+        .1
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+}
+
+/// Accepts the token as a bearer header or, for clients like `EventSource`
+/// that can't set headers, as a `token` query parameter.
+pub fn authorized(authorization: Option<&str>, url: &str, token: &str) -> bool {
+    if token.is_empty() {
+        return false;
+    }
+    let presented = authorization.and_then(|value| value.strip_prefix("Bearer ")).or_else(|| query_param(url, "token"));
+    presented.is_some_and(|presented| constant_time_eq(presented.trim().as_bytes(), token.as_bytes()))
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+pub fn sse_event(event: &str, data: &str) -> String {
+    format!("event: {event}\ndata: {data}\n\n")
+}
+
+/// One `status` event on `/events`: sent on connect, then whenever the status
+/// changes or the engine reports something.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusEvent {
+    #[serde(flatten)]
+    pub status: StatusReport,
+    pub events: Vec<EngineEvent>,
+}
+
+struct Running {
+    settings: LocalApiSettings,
+    server: Arc<Server>,
+    thread: JoinHandle<()>,
+}
+
+/// The server, if one is running, and the open `/events` streams.
+#[derive(Default)]
+pub struct LocalApi {
+    running: Mutex<Option<Running>>,
+    subscribers: Mutex<Vec<Sender<String>>>,
+    last_status: Mutex<Option<String>>,
+}
+
+/// Starts, restarts or stops the server to match `settings`. A port that is
+/// already taken leaves the API off until the settings change again.
+pub fn configure(app: &AppHandle, settings: &LocalApiSettings) {
+    let Some(api) = app.try_state::<LocalApi>() else {
+        return;
+    };
+    let mut running = api.running.lock().unwrap();
+    if running.as_ref().is_some_and(|running| running.settings == *settings) {
+        return;
+    }
+    if let Some(Running { server, thread, .. }) = running.take() {
+        server.unblock();
+        drop(server);
+        let _ = thread.join();
+        api.subscribers.lock().unwrap().clear();
+    }
+    if !settings.enabled || settings.validate().is_err() {
+        return;
+    }
+    // The old listener closes on its own accept thread; give it a moment.
+    let server = (0..5).find_map(|attempt| {
+        if attempt > 0 {
+            thread::sleep(Duration::from_millis(100));
+        }
+        Server::http((Ipv4Addr::LOCALHOST, settings.port)).ok()
+    });
+    let Some(server) = server.map(Arc::new) else {
+        return;
+    };
+    let thread = {
+        let (app, server, settings) = (app.clone(), server.clone(), settings.clone());
+        thread::spawn(move || {
+            for request in server.incoming_requests() {
+                let (app, settings) = (app.clone(), settings.clone());
+                thread::spawn(move || handle(&app, request, &settings));
+            }
+        })
+    };
+    *running = Some(Running { settings: settings.clone(), server, thread });
+}
+
+/// Called from `monitor::publish`; feeds the `/events` streams.
+pub fn broadcast(app: &AppHandle, update: &FatigueUpdate) {
+    let Some(api) = app.try_state::<LocalApi>() else {
+        return;
+    };
+    let mut subscribers = api.subscribers.lock().unwrap();
+    if subscribers.is_empty() {
+        return;
+    }
+    let status = cli::status_report(app);
+    let Ok(status_json) = serde_json::to_string(&status) else {
+        return;
+    };
+    {
+        let mut last_status = api.last_status.lock().unwrap();
+        if update.events.is_empty() && last_status.as_deref() == Some(status_json.as_str()) {
+            return;
+        }
+        *last_status = Some(status_json);
+    }
+    let Ok(data) = serde_json::to_string(&StatusEvent { status, events: update.events.clone() }) else {
+        return;
+    };
+    let event = sse_event("status", &data);
+    subscribers.retain(|subscriber| subscriber.send(event.clone()).is_ok());
+}
+
+fn handle(app: &AppHandle, mut request: Request, settings: &LocalApiSettings) {
+    let authorization = request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv("Authorization"))
+        .map(|header| header.value.as_str().to_string());
+    if !authorized(authorization.as_deref(), request.url(), &settings.token) {
+        respond_error(request, &ApiError::new(401, "missing or wrong token"));
+        return;
+    }
+    let mut body = String::new();
+    let _ = request.as_reader().take(MAX_BODY_BYTES).read_to_string(&mut body);
+    match route(request.method().as_str(), request.url(), &body) {
+        Ok(Route::Status) => respond_json(request, 200, &cli::status_report(app)),
+        Ok(Route::Events) => stream_events(app, request),
+        Ok(Route::Metrics) if settings.metrics => {
+            let state = app.state::<Mutex<FatigueEngine>>().lock().unwrap().snapshot();
+            let body = app.try_state::<Mutex<Metrics>>().map(|metrics| metrics.lock().unwrap().render(&state));
+            let content_type = Header::from_bytes("Content-Type", metrics::CONTENT_TYPE).unwrap();
+            let _ = request.respond(Response::from_string(body.unwrap_or_default()).with_header(content_type));
+        }
+        Ok(Route::Metrics) => respond_error(request, &ApiError::new(404, "metrics are turned off")),
+        Ok(Route::Command(command)) => {
+            let reply = cli::execute(app, &CliRequest { command, json: true, reply_to: None, nonce: None });
+            respond_json(request, if reply.ok { 200 } else { 400 }, &reply);
+        }
+        Err(error) => respond_error(request, &error),
+    }
+}
+
+fn respond_json<T: Serialize>(request: Request, status: u16, body: &T) {
+    let Ok(json) = serde_json::to_string(body) else {
+        return;
+    };
+    let content_type = Header::from_bytes("Content-Type", "application/json").unwrap();
+    let _ = request.respond(Response::from_string(json).with_status_code(status).with_header(content_type));
+}
+
+fn respond_error(request: Request, error: &ApiError) {
+    respond_json(request, error.status, &serde_json::json!({ "error": error.message }));
+}
+
+/// Writes the response by hand: tiny_http's chunked encoder buffers 8 KB,
+/// which would hold events back.
+fn stream_events(app: &AppHandle, request: Request) {
+    let Some(api) = app.try_state::<LocalApi>() else {
+        return;
+    };
+    let status = StatusEvent { status: cli::status_report(app), events: Vec::new() };
+    let Ok(first) = serde_json::to_string(&status) else {
+        return;
+    };
+    let (sender, receiver) = mpsc::channel();
+    api.subscribers.lock().unwrap().push(sender);
+
+    let mut writer = request.into_writer();
+    let head = "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n";
+    let mut chunk = format!("{head}{}", sse_event("status", &first));
+    // Returning drops the receiver, which unsubscribes on the next broadcast.
+    while writer.write_all(chunk.as_bytes()).and_then(|_| writer.flush()).is_ok() {
+        chunk = match receiver.recv_timeout(KEEP_ALIVE_INTERVAL) {
+            Ok(event) => event,
+            Err(RecvTimeoutError::Timeout) => ": keep-alive\n\n".to_string(),
+            Err(RecvTimeoutError::Disconnected) => return,
+        };
+    }
+}
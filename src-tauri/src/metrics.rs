@@ -0,0 +1,69 @@
+use crate::fatigue::FatigueSnapshot;
+use crate::pavlok::{PavlokError, Stimulus};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+/// Counters since launch; Prometheus handles the reset on restart.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    stimuli_sent: BTreeMap<&'static str, u64>,
+    api_failures: BTreeMap<String, u64>,
+}
+
+impl Metrics {
+    pub fn record_stimulus(&mut self, stimulus: Stimulus, result: &Result<(), PavlokError>) {
+        match result {
+            Ok(()) => *self.stimuli_sent.entry(stimulus.as_str()).or_default() += 1,
+            Err(error) => *self.api_failures.entry(failure_status(error)).or_default() += 1,
+        }
+    }
+
+    /// The Prometheus text format for the current state plus the counters.
+    pub fn render(&self, state: &FatigueSnapshot) -> String {
+        let mut out = String::new();
+        let mut gauge = |name: &str, help: &str, value: f64| {
+            let _ = writeln!(out, "# HELP {name} {help}\n# TYPE {name} gauge\n{name} {value}");
+        };
+        gauge("touch_grass_fatigue_percent", "Fatigue as a percentage of the WORK limit.", state.percent);
+        gauge("touch_grass_fatigue_minutes", "Fatigue in minutes.", state.fatigue as f64);
+        gauge("touch_grass_rest_streak_minutes", "Minutes idle in a row.", state.rest_streak as f64);
+        gauge("touch_grass_monitoring", "1 while monitoring, 0 when paused.", if state.monitoring { 1.0 } else { 0.0 });
+
+        let _ = writeln!(out, "# HELP touch_grass_stimuli_sent_total Stimuli the Pavlok API accepted.");
+        let _ = writeln!(out, "# TYPE touch_grass_stimuli_sent_total counter");
+        // Every type is listed so rate() works from the first scrape.
+        for stimulus in [Stimulus::Beep, Stimulus::Vibe, Stimulus::Zap] {
+            let count = self.stimuli_sent.get(stimulus.as_str()).copied().unwrap_or_default();
+            let _ = writeln!(out, "touch_grass_stimuli_sent_total{{type=\"{}\"}} {count}", stimulus.as_str());
+        }
+
+        let _ = writeln!(out, "# HELP touch_grass_api_failures_total Failed Pavlok API requests by HTTP status.");
+        let _ = writeln!(out, "# TYPE touch_grass_api_failures_total counter");
+        for (status, count) in &self.api_failures {
+            let _ = writeln!(out, "touch_grass_api_failures_total{{status=\"{status}\"}} {count}");
+        }
+        out
+    }
+}
+
+/// The `status` label: the HTTP status where there was one, otherwise
+/// `network` or `invalid_token`. 403 is reported as 401.
+pub fn failure_status(error: &PavlokError) -> String {
+    match error {
+        PavlokError::Unauthorized => "401".into(),
+        PavlokError::RateLimited { .. } => "429".into(),
+        PavlokError::Server { status } => status.to_string(),
+        PavlokError::Network { .. } => "network".into(),
+        PavlokError::InvalidToken => "invalid_token".into(),
+    }
+}
+
+pub fn record_stimulus(app: &AppHandle, stimulus: Stimulus, result: &Result<(), PavlokError>) {
+    if let Some(metrics) = app.try_state::<Mutex<Metrics>>() {
+        metrics.lock().unwrap().record_stimulus(stimulus, result);
+    }
+}
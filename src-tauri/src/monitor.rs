@@ -0,0 +1,200 @@
+use crate::alert_queue::now_ms;
+use crate::escalation::Escalation;
+use crate::fatigue::{EngineEvent, FatigueEngine, FatigueSnapshot, FatigueUpdate, MinuteSample, PersistedState};
+use crate::history::{History, HistoryRecord};
+use crate::intensity::Intensities;
+use crate::local_api;
+use crate::mqtt;
+use crate::notifier::{self, Alert, AlertEvent};
+use crate::pavlok::Stimulus;
+use crate::settings::SettingsStore;
+use crate::snooze;
+use crate::tray_controls::TrayControls;
+use crate::tray_status;
+use crate::webhooks;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+use tauri::{AppHandle, Emitter, Manager};
+
+pub const FATIGUE_UPDATED_EVENT: &str = "fatigue-updated";
+/// Carries the outcome of each alert sent for an engine event: `null` when it
+/// went out, otherwise the `PavlokError`.
+pub const ALERT_RESULT_EVENT: &str = "alert-result";
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+// How often engine state is written out; a crash loses at most this much.
+const SAVE_EVERY_TICKS: u32 = 30;
+
+/// Starts the 1-second sampling loop. It runs on its own thread so accounting
+/// keeps going while the widget is hidden and webview timers are throttled.
+pub fn spawn_sampler(app: AppHandle) {
+    thread::spawn(move || {
+        let mut next_tick = Instant::now() + SAMPLE_INTERVAL;
+        let mut last_sample = (Instant::now(), SystemTime::now());
+        let mut ticks_since_save = 0;
+        loop {
+            thread::sleep(next_tick.saturating_duration_since(Instant::now()));
+            next_tick += SAMPLE_INTERVAL;
+            // After a long stall (sleep, debugger), resync instead of bursting ticks.
+            if next_tick < Instant::now() {
+                next_tick = Instant::now() + SAMPLE_INTERVAL;
+            }
+
+            // Monotonic time stops during suspend on some platforms; the wall
+            // clock doesn't, so the difference is time asleep the engine never saw.
+            let (mono_before, wall_before) = last_sample;
+            last_sample = (Instant::now(), SystemTime::now());
+            let mono_gap = last_sample.0.saturating_duration_since(mono_before);
+            let wall_gap = last_sample.1.duration_since(wall_before).unwrap_or_default();
+            let unseen_minutes = (wall_gap.saturating_sub(mono_gap).as_secs() / 60) as u32;
+
+            let (update, minutes) = {
+                let engine = app.state::<Mutex<FatigueEngine>>();
+                let mut engine = engine.lock().unwrap();
+                let mut events = engine.apply_rest(unseen_minutes);
+                events.extend(engine.tick());
+                (FatigueUpdate { state: engine.snapshot(), events }, engine.take_finished_minutes())
+            };
+            snooze::tick(&app);
+            publish(&app, &update);
+            record_minutes(&app, minutes);
+
+            ticks_since_save += 1;
+            if ticks_since_save >= SAVE_EVERY_TICKS || unseen_minutes > 0 {
+                ticks_since_save = 0;
+                save_state(&app);
+            }
+        }
+    });
+}
+
+fn record_minutes(app: &AppHandle, minutes: Vec<MinuteSample>) {
+    if minutes.is_empty() {
+        return;
+    }
+    // Several minutes can finish in one tick after a stall; space them out so they end now.
+    let now = now_ms();
+    let last = minutes.len() as u64 - 1;
+    let records: Vec<HistoryRecord> = minutes
+        .into_iter()
+        .enumerate()
+        .map(|(index, sample)| HistoryRecord::Minute { at_ms: now.saturating_sub((last - index as u64) * 60_000), sample })
+        .collect();
+    if let Some(history) = app.try_state::<Mutex<History>>() {
+        history.lock().unwrap().append_all(&records);
+    }
+}
+
+fn state_file(app: &AppHandle) -> Option<PathBuf> {
+    let mut dir = app.path().app_data_dir().ok()?;
+    dir.push("engine-state.json");
+    Some(dir)
+}
+
+pub fn save_state(app: &AppHandle) {
+    let Some(path) = state_file(app) else {
+        return;
+    };
+    let state = app.state::<Mutex<FatigueEngine>>().lock().unwrap().persisted_state(now_ms());
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    if let Ok(json) = serde_json::to_string(&state) {
+        let _ = fs::write(path, json);
+    }
+}
+
+/// Loads the previous run's state, if any. Call after the limits are applied.
+pub fn restore_state(app: &AppHandle) {
+    let state = state_file(app)
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|raw| serde_json::from_str::<PersistedState>(&raw).ok());
+    if let Some(state) = state {
+        app.state::<Mutex<FatigueEngine>>().lock().unwrap().restore(&state, now_ms());
+    }
+}
+
+/// Starts or pauses monitoring from the widget or the tray.
+pub fn set_monitoring(app: &AppHandle, enabled: bool) -> FatigueSnapshot {
+    let (state, changed) = {
+        let engine = app.state::<Mutex<FatigueEngine>>();
+        let mut engine = engine.lock().unwrap();
+        let changed = engine.snapshot().monitoring != enabled;
+        engine.set_monitoring(enabled);
+        (engine.snapshot(), changed)
+    };
+    publish(app, &FatigueUpdate { state: state.clone(), events: Vec::new() });
+    if changed {
+        webhooks::monitoring_changed(app, &state);
+    }
+    state
+}
+
+pub fn reset(app: &AppHandle) -> FatigueSnapshot {
+    let update = {
+        let engine = app.state::<Mutex<FatigueEngine>>();
+        let mut engine = engine.lock().unwrap();
+        let events = engine.reset();
+        FatigueUpdate { state: engine.snapshot(), events }
+    };
+    publish(app, &update);
+    update.state
+}
+
+pub fn publish(app: &AppHandle, update: &FatigueUpdate) {
+    // A full rest starts the escalation ladder over.
+    if update.events.contains(&EngineEvent::Rested) {
+        if let Some(escalation) = app.try_state::<Mutex<Escalation>>() {
+            escalation.lock().unwrap().reset();
+        }
+    }
+    send_alerts(app, update);
+    tray_status::update(app, &update.state);
+    if let Some(controls) = app.try_state::<TrayControls>() {
+        controls.sync_monitoring(update.state.monitoring);
+    }
+    local_api::broadcast(app, update);
+    mqtt::broadcast(app);
+    webhooks::engine_events(app, update);
+    let _ = app.emit_to("main", FATIGUE_UPDATED_EVENT, update);
+}
+
+/// Sends the alerts for an update's events through the notifiers chosen for
+/// each: the alert mode (or the escalation ladder's step) at the limit, a vibe
+/// once rested. They go out from here rather than the widget, one after
+/// another on a worker thread so the sampler never waits on the network.
+/// While snoozed only the webhook notifier runs.
+fn send_alerts(app: &AppHandle, update: &FatigueUpdate) {
+    if update.events.is_empty() {
+        return;
+    }
+    let snoozed = snooze::is_snoozed(app);
+    let mode = app.state::<Mutex<SettingsStore>>().lock().unwrap().get().alert_mode;
+    let intensities = *app.state::<Mutex<Intensities>>().lock().unwrap();
+    let overshoot_minutes = update.state.fatigue.saturating_sub(update.state.work_minutes);
+    let events = update.events.clone();
+    let state = update.state.clone();
+    let app = app.clone();
+    thread::spawn(move || {
+        for event in events {
+            let (event, stimulus, intensity) = match event {
+                // A snoozed alert doesn't climb the escalation ladder.
+                EngineEvent::LimitReached if snoozed => (AlertEvent::LimitReached, mode, intensities.get(mode)),
+                EngineEvent::LimitReached => {
+                    let escalation = app.state::<Mutex<Escalation>>();
+                    let (stimulus, intensity) = escalation.lock().unwrap().next_alert(mode, overshoot_minutes, &intensities);
+                    (AlertEvent::LimitReached, stimulus, intensity)
+                }
+                EngineEvent::Rested => (AlertEvent::Rested, Stimulus::Vibe, intensities.get(Stimulus::Vibe)),
+            };
+            let alert = Alert { event, stimulus, intensity, state: state.clone() };
+            let result = notifier::send(&app, &alert, snoozed);
+            // Nothing reached the Pavlok, so there is no outcome to show.
+            if !snoozed {
+                let _ = app.emit_to("main", ALERT_RESULT_EVENT, result.err());
+            }
+        }
+    });
+}
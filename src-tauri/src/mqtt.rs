@@ -0,0 +1,278 @@
+use crate::cli::{self, CliCommand, CliRequest};
+use rumqttc::{Client, Event, LastWill, MqttOptions, Outgoing, Packet, QoS, Transport};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+pub const DEFAULT_PORT: u16 = 1883;
+const KEEP_ALIVE: Duration = Duration::from_secs(30);
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+// Requests queued for the connection thread; enough for a full announce.
+const REQUEST_CAPACITY: usize = 32;
+const ONLINE: &str = "online";
+const OFFLINE: &str = "offline";
+
+/// `[mqtt]` in settings.toml.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct MqttSettings {
+    pub enabled: bool,
+    pub host: String,
+    pub port: u16,
+    pub tls: bool,
+    pub username: Option<String>,
+    /// Kept in the keyring; settings.toml and `get_settings` only show `hasPassword`.
+    #[serde(rename(serialize = "hasPassword"), serialize_with = "crate::settings::serialize_option_is_set")]
+    pub password: Option<String>,
+    pub client_id: String,
+    /// State goes to `<baseTopic>/state`, commands come in on `<baseTopic>/command`.
+    pub base_topic: String,
+    /// Publish Home Assistant discovery configs under this prefix; empty turns discovery off.
+    pub discovery_prefix: String,
+}
+
+impl Default for MqttSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            host: "localhost".into(),
+            port: DEFAULT_PORT,
+            tls: false,
+            username: None,
+            password: None,
+            client_id: "pavlok-touch-grass".into(),
+            base_topic: "touch_grass".into(),
+            discovery_prefix: "homeassistant".into(),
+        }
+    }
+}
+
+impl MqttSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.enabled && self.host.trim().is_empty() {
+            return Err("mqtt.host must be set".into());
+        }
+        if self.port == 0 {
+            return Err("mqtt.port must be between 1 and 65535".into());
+        }
+        if self.client_id.trim().is_empty() {
+            return Err("mqtt.clientId must not be empty".into());
+        }
+        let base = self.base_topic.trim_matches('/');
+        if base.is_empty() || base.contains(['+', '#']) {
+            return Err(format!("mqtt.baseTopic must be a plain topic, got {:?}", self.base_topic));
+        }
+        Ok(())
+    }
+
+    pub fn topic(&self, leaf: &str) -> String {
+        format!("{}/{leaf}", self.base_topic.trim_matches('/'))
+    }
+
+    pub fn availability_topic(&self) -> String {
+        self.topic("status")
+    }
+
+    pub fn state_topic(&self) -> String {
+        self.topic("state")
+    }
+
+    pub fn command_topic(&self) -> String {
+        self.topic("command")
+    }
+
+    /// Home Assistant only allows `[a-zA-Z0-9_-]` in node ids.
+    fn node_id(&self) -> String {
+        self.client_id.chars().map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' }).collect()
+    }
+}
+
+/// `start`, `stop` and `reset`, in any case. Anything else is ignored.
+pub fn parse_command(payload: &[u8]) -> Option<CliCommand> {
+    match std::str::from_utf8(payload).ok()?.trim().to_ascii_lowercase().as_str() {
+        "start" => Some(CliCommand::Start),
+        "stop" => Some(CliCommand::Stop),
+        "reset" => Some(CliCommand::Reset),
+        _ => None,
+    }
+}
+
+/// Retained discovery messages as (topic, payload): fatigue, percent and rest
+/// streak sensors, an at-limit binary sensor, a monitoring switch and a reset button.
+pub fn discovery_configs(settings: &MqttSettings) -> Vec<(String, Value)> {
+    let prefix = settings.discovery_prefix.trim_matches('/');
+    if prefix.is_empty() {
+        return Vec::new();
+    }
+    let node = settings.node_id();
+    let device = json!({
+        "identifiers": [node],
+        "name": "Pavlok: Touch Grass",
+        "manufacturer": "Pavlok: Touch Grass",
+        "sw_version": env!("CARGO_PKG_VERSION"),
+    });
+    let entity = |component: &str, object: &str, name: &str, extra: Value| {
+        let mut config = json!({
+            "name": name,
+            "unique_id": format!("{node}_{object}"),
+            "object_id": format!("{node}_{object}"),
+            "availability_topic": settings.availability_topic(),
+            "device": device,
+        });
+        if let (Some(config), Some(extra)) = (config.as_object_mut(), extra.as_object()) {
+            config.extend(extra.clone());
+        }
+        (format!("{prefix}/{component}/{node}/{object}/config"), config)
+    };
+    let state = settings.state_topic();
+    let command = settings.command_topic();
+    vec![
+        entity("sensor", "fatigue_percent", "Fatigue", json!({
+            "state_topic": state,
+            "value_template": "{{ value_json.percent | round(0) }}",
+            "unit_of_measurement": "%",
+            "state_class": "measurement",
+            "icon": "mdi:lightning-bolt",
+        })),
+        entity("sensor", "fatigue_minutes", "Fatigue minutes", json!({
+            "state_topic": state,
+            "value_template": "{{ value_json.fatigue }}",
+            "unit_of_measurement": "min",
+            "device_class": "duration",
+            "state_class": "measurement",
+        })),
+        entity("sensor", "rest_streak", "Rest streak", json!({
+            "state_topic": state,
+            "value_template": "{{ value_json.restStreak }}",
+            "unit_of_measurement": "min",
+            "device_class": "duration",
+            "state_class": "measurement",
+        })),
+        entity("binary_sensor", "at_limit", "At limit", json!({
+            "state_topic": state,
+            "value_template": "{{ 'ON' if value_json.atLimit else 'OFF' }}",
+            "device_class": "problem",
+        })),
+        entity("switch", "monitoring", "Monitoring", json!({
+            "state_topic": state,
+            "value_template": "{{ 'ON' if value_json.monitoring else 'OFF' }}",
+            "command_topic": command,
+            "payload_on": "start",
+            "payload_off": "stop",
+        })),
+        entity("button", "reset", "Reset fatigue", json!({
+            "command_topic": command,
+            "payload_press": "reset",
+        })),
+    ]
+}
+
+struct Running {
+    settings: MqttSettings,
+    client: Client,
+    stop: Arc<AtomicBool>,
+}
+
+/// The broker connection, if any, and the last state sent so unchanged
+/// samples aren't republished every second.
+#[derive(Default)]
+pub struct Mqtt {
+    running: Mutex<Option<Running>>,
+    last_state: Mutex<Option<String>>,
+}
+
+/// Connects, reconnects or disconnects to match `settings`. Called after every
+/// settings change, like `local_api::configure`.
+pub fn configure(app: &AppHandle, settings: &MqttSettings) {
+    let Some(mqtt) = app.try_state::<Mqtt>() else {
+        return;
+    };
+    let mut running = mqtt.running.lock().unwrap();
+    if running.as_ref().is_some_and(|running| running.settings == *settings) {
+        return;
+    }
+    if let Some(old) = running.take() {
+        let _ = old.client.try_publish(old.settings.availability_topic(), QoS::AtLeastOnce, true, OFFLINE);
+        let _ = old.client.try_disconnect();
+        old.stop.store(true, Ordering::Relaxed);
+    }
+    *mqtt.last_state.lock().unwrap() = None;
+    if !settings.enabled || settings.validate().is_err() {
+        return;
+    }
+
+    let mut options = MqttOptions::new(settings.client_id.trim(), settings.host.trim(), settings.port);
+    options.set_keep_alive(KEEP_ALIVE);
+    options.set_last_will(LastWill::new(settings.availability_topic(), OFFLINE, QoS::AtLeastOnce, true));
+    if let Some(username) = settings.username.as_deref().filter(|username| !username.is_empty()) {
+        options.set_credentials(username, settings.password.clone().unwrap_or_default());
+    }
+    if settings.tls {
+        options.set_transport(Transport::tls_with_default_config());
+    }
+    let (client, mut connection) = Client::new(options, REQUEST_CAPACITY);
+    let stop = Arc::new(AtomicBool::new(false));
+
+    {
+        let (app, client, settings, stop) = (app.clone(), client.clone(), settings.clone(), stop.clone());
+        thread::spawn(move || {
+            // The iterator reconnects by itself on the next call after an error.
+            for event in connection.iter() {
+                match event {
+                    Ok(Event::Incoming(Packet::ConnAck(_))) => announce(&app, &client, &settings),
+                    Ok(Event::Incoming(Packet::Publish(publish))) if publish.topic == settings.command_topic() => {
+                        if let Some(command) = parse_command(&publish.payload) {
+                            cli::execute(&app, &CliRequest { command, json: true, reply_to: None, nonce: None });
+                        }
+                    }
+                    // Sent by `configure` on a settings change, after "offline" went out.
+                    Ok(Event::Outgoing(Outgoing::Disconnect)) => break,
+                    Ok(_) => {}
+                    // Down and never got to send the disconnect.
+                    Err(_) if stop.load(Ordering::Relaxed) => break,
+                    Err(_) => thread::sleep(RECONNECT_DELAY),
+                }
+            }
+        });
+    }
+    *running = Some(Running { settings: settings.clone(), client, stop });
+}
+
+/// On every (re)connect: subscribe, mark online, send discovery and the current state.
+/// Runs on the connection thread, which is the one draining the queue, so it must not block.
+fn announce(app: &AppHandle, client: &Client, settings: &MqttSettings) {
+    let _ = client.try_subscribe(settings.command_topic(), QoS::AtLeastOnce);
+    let _ = client.try_publish(settings.availability_topic(), QoS::AtLeastOnce, true, ONLINE);
+    for (topic, config) in discovery_configs(settings) {
+        let _ = client.try_publish(topic, QoS::AtLeastOnce, true, config.to_string());
+    }
+    if let Ok(state) = serde_json::to_string(&cli::status_report(app)) {
+        let _ = client.try_publish(settings.state_topic(), QoS::AtLeastOnce, true, state);
+    }
+}
+
+/// Called from `monitor::publish`; sends the state when it changed.
+pub fn broadcast(app: &AppHandle) {
+    let Some(mqtt) = app.try_state::<Mqtt>() else {
+        return;
+    };
+    let running = mqtt.running.lock().unwrap();
+    let Some(running) = running.as_ref() else {
+        return;
+    };
+    let Ok(state) = serde_json::to_string(&cli::status_report(app)) else {
+        return;
+    };
+    let mut last_state = mqtt.last_state.lock().unwrap();
+    if last_state.as_deref() == Some(state.as_str()) {
+        return;
+    }
+    // Never blocks the sampler; a full queue drops this sample and the next change retries.
+    if running.client.try_publish(running.settings.state_topic(), QoS::AtLeastOnce, true, state.clone()).is_ok() {
+        *last_state = Some(state);
+    }
+}
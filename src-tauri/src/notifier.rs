@@ -0,0 +1,343 @@
+use crate::alert_queue::{self, StimulusQueue};
+use crate::fatigue::FatigueSnapshot;
+use crate::history;
+use crate::metrics;
+use crate::pavlok::{Intensity, PavlokClient, PavlokError, Stimulus};
+use crate::settings::SettingsStore;
+use crate::token_store::TokenStore;
+use crate::webhooks::{self, Webhook, WebhookEvent, WebhookPayload};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_notification::NotificationExt;
+
+/// Reason sent with a stimulus the user asked for directly.
+pub const MANUAL_REASON: &str = "Manual alert";
+// Gap between the two vibes that say "rested", so they feel like a pattern.
+const RESTED_VIBE_GAP: Duration = Duration::from_millis(200);
+
+/// Engine events that alert the user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AlertEvent {
+    LimitReached,
+    /// Fatigue is back to zero; the break is over.
+    Rested,
+}
+
+impl AlertEvent {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AlertEvent::LimitReached => "limitReached",
+            AlertEvent::Rested => "rested",
+        }
+    }
+
+    pub fn webhook_event(self) -> WebhookEvent {
+        match self {
+            AlertEvent::LimitReached => WebhookEvent::LimitReached,
+            AlertEvent::Rested => WebhookEvent::Rested,
+        }
+    }
+
+    /// Sent to the Pavlok API with the stimulus and kept in the history.
+    pub fn reason(self) -> &'static str {
+        match self {
+            AlertEvent::LimitReached => "Fatigue limit",
+            AlertEvent::Rested => "Rested",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotifierKind {
+    Pavlok,
+    Desktop,
+    Sound,
+    Script,
+    Webhook,
+}
+
+/// `[notifiers]` in settings.toml: which backends fire for each event, and
+/// what the sound and script backends use.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct NotifierSettings {
+    pub limit_reached: Vec<NotifierKind>,
+    pub rested: Vec<NotifierKind>,
+    /// Played by the sound backend; the system's alert sound when unset.
+    pub sound_file: Option<PathBuf>,
+    /// Run by the script backend with the alert in `TOUCH_GRASS_*` variables.
+    pub script: Option<PathBuf>,
+}
+
+impl Default for NotifierSettings {
+    fn default() -> Self {
+        Self {
+            limit_reached: vec![NotifierKind::Pavlok],
+            rested: vec![NotifierKind::Pavlok],
+            sound_file: None,
+            script: None,
+        }
+    }
+}
+
+impl NotifierSettings {
+    /// `webhooks` are the `[[webhooks]]` entries the webhook backend sends to.
+    pub fn validate(&self, webhooks: &[Webhook]) -> Result<(), String> {
+        let selected = |kind| self.limit_reached.contains(&kind) || self.rested.contains(&kind);
+        if selected(NotifierKind::Script) && self.script.is_none() {
+            return Err("notifiers.script must be set to use the script notifier".into());
+        }
+        for event in [AlertEvent::LimitReached, AlertEvent::Rested] {
+            let subscribed = webhooks.iter().any(|webhook| webhook.wants(event.webhook_event()));
+            if self.for_event(event).contains(&NotifierKind::Webhook) && !subscribed {
+                return Err(format!("notifiers.{} uses webhook but no [[webhooks]] entry subscribes to it", event.as_str()));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn for_event(&self, event: AlertEvent) -> &[NotifierKind] {
+        match event {
+            AlertEvent::LimitReached => &self.limit_reached,
+            AlertEvent::Rested => &self.rested,
+        }
+    }
+
+    /// The backends that fire for `event` right now. A snooze holds off the
+    /// ones aimed at the user but not the webhook, so integrations still
+    /// see every event.
+    pub fn active_for(&self, event: AlertEvent, snoozed: bool) -> Vec<NotifierKind> {
+        let held = |kind: &NotifierKind| snoozed && *kind != NotifierKind::Webhook;
+        self.for_event(event).iter().copied().filter(|kind| !held(kind)).collect()
+    }
+
+    /// The backend for `kind`, or `None` when its configuration is missing.
+    pub fn build(&self, kind: NotifierKind) -> Option<Box<dyn Notifier>> {
+        Some(match kind {
+            NotifierKind::Pavlok => Box::new(PavlokNotifier),
+            NotifierKind::Desktop => Box::new(DesktopNotifier),
+            NotifierKind::Sound => Box::new(SoundNotifier { file: self.sound_file.clone() }),
+            NotifierKind::Script => Box::new(ScriptNotifier { path: self.script.clone()? }),
+            NotifierKind::Webhook => Box::new(WebhookNotifier),
+        })
+    }
+}
+
+/// What every backend gets. `stimulus` and `intensity` are what Pavlok would
+/// send; other backends may pass them on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Alert {
+    pub event: AlertEvent,
+    pub stimulus: Stimulus,
+    pub intensity: Intensity,
+    pub state: FatigueSnapshot,
+}
+
+impl Alert {
+    pub fn title(&self) -> &'static str {
+        match self.event {
+            AlertEvent::LimitReached => "Time to touch grass",
+            AlertEvent::Rested => "Fully rested",
+        }
+    }
+
+    pub fn body(&self) -> String {
+        match self.event {
+            AlertEvent::LimitReached => format!(
+                "Fatigue is at {}%. Take a {} minute break.",
+                self.state.percent.round(),
+                self.state.break_minutes
+            ),
+            AlertEvent::Rested => "Fatigue is back to zero.".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NotifyError {
+    /// Kept as is so the widget can flag a bad API key.
+    Pavlok(PavlokError),
+    Failed(String),
+}
+
+pub trait Notifier {
+    /// Delivers `alert`. Called on a blocking worker, never the main thread.
+    fn notify(&self, app: &AppHandle, alert: &Alert) -> Result<(), NotifyError>;
+}
+
+/// Runs every backend selected for the alert's event, only the webhook while
+/// `snoozed`. Only the Pavlok result is reported back; the other backends
+/// fail quietly.
+pub fn send(app: &AppHandle, alert: &Alert, snoozed: bool) -> Result<(), PavlokError> {
+    let settings = match app.try_state::<Mutex<SettingsStore>>() {
+        Some(store) => store.lock().unwrap().get().notifiers.clone(),
+        None => NotifierSettings::default(),
+    };
+    let mut result = Ok(());
+    for kind in settings.active_for(alert.event, snoozed) {
+        let Some(notifier) = settings.build(kind) else {
+            continue;
+        };
+        if let Err(NotifyError::Pavlok(error)) = notifier.notify(app, alert) {
+            result = Err(error);
+        }
+    }
+    result
+}
+
+/// Sends one stimulus right away; transient failures are also queued for
+/// retry in the background. `reason` goes to the API, the history and the queue.
+pub fn send_pavlok(app: &AppHandle, stimulus: Stimulus, intensity: Intensity, reason: &str) -> Result<(), PavlokError> {
+    let token = app.state::<TokenStore>().token().ok_or(PavlokError::InvalidToken)?;
+
+    let result = app.state::<PavlokClient>().send_stimulus(&token, stimulus, intensity, reason);
+    history::record_stimulus(app, stimulus, intensity, reason, &result, false);
+    metrics::record_stimulus(app, stimulus, &result);
+    webhooks::stimulus(app, stimulus, intensity, &result);
+    let queue = app.state::<Mutex<StimulusQueue>>();
+    let mut queue = queue.lock().unwrap();
+    match &result {
+        Ok(()) => queue.delivered(stimulus, reason),
+        Err(error) => {
+            queue.push_failed(stimulus, intensity, reason, error, alert_queue::now_ms());
+        }
+    }
+    result
+}
+
+pub struct PavlokNotifier;
+
+impl Notifier for PavlokNotifier {
+    /// Rested is two short vibes in a row.
+    fn notify(&self, app: &AppHandle, alert: &Alert) -> Result<(), NotifyError> {
+        let reason = alert.event.reason();
+        send_pavlok(app, alert.stimulus, alert.intensity, reason).map_err(NotifyError::Pavlok)?;
+        if alert.event == AlertEvent::Rested {
+            thread::sleep(RESTED_VIBE_GAP);
+            send_pavlok(app, alert.stimulus, alert.intensity, reason).map_err(NotifyError::Pavlok)?;
+        }
+        Ok(())
+    }
+}
+
+pub struct DesktopNotifier;
+
+impl Notifier for DesktopNotifier {
+    fn notify(&self, app: &AppHandle, alert: &Alert) -> Result<(), NotifyError> {
+        app.notification()
+            .builder()
+            .title(alert.title())
+            .body(alert.body())
+            .show()
+            .map_err(|e| NotifyError::Failed(e.to_string()))
+    }
+}
+
+pub struct SoundNotifier {
+    pub file: Option<PathBuf>,
+}
+
+impl Notifier for SoundNotifier {
+    fn notify(&self, _app: &AppHandle, _alert: &Alert) -> Result<(), NotifyError> {
+        spawn_detached(sound_command(self.file.as_ref()))
+    }
+}
+
+/// Uses the OS player (PulseAudio or PipeWire tools on Linux), so no audio stack is bundled.
+fn sound_command(file: Option<&PathBuf>) -> Command {
+    #[cfg(target_os = "macos")]
+    {
+        let mut command = Command::new("afplay");
+        command.arg(file.cloned().unwrap_or_else(|| "/System/Library/Sounds/Glass.aiff".into()));
+        command
+    }
+    #[cfg(target_os = "windows")]
+    {
+        let script = match file {
+            Some(file) => format!("(New-Object Media.SoundPlayer '{}').PlaySync()", file.display().to_string().replace('\'', "''")),
+            None => "[System.Media.SystemSounds]::Exclamation.Play(); Start-Sleep -Milliseconds 500".to_string(),
+        };
+        let mut command = Command::new("powershell");
+        command.args(["-NoProfile", "-NonInteractive", "-Command", &script]);
+        command
+    }
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    {
+        match file {
+            Some(file) => {
+                let mut command = Command::new("paplay");
+                command.arg(file);
+                command
+            }
+            None => {
+                let mut command = Command::new("canberra-gtk-play");
+                command.args(["--id", "complete"]);
+                command
+            }
+        }
+    }
+}
+
+pub struct ScriptNotifier {
+    pub path: PathBuf,
+}
+
+impl Notifier for ScriptNotifier {
+    fn notify(&self, _app: &AppHandle, alert: &Alert) -> Result<(), NotifyError> {
+        let mut command = Command::new(&self.path);
+        command.arg(alert.event.as_str());
+        for (name, value) in script_env(alert) {
+            command.env(name, value);
+        }
+        spawn_detached(command)
+    }
+}
+
+/// What the script backend passes in the environment.
+pub fn script_env(alert: &Alert) -> Vec<(&'static str, String)> {
+    vec![
+        ("TOUCH_GRASS_EVENT", alert.event.as_str().to_string()),
+        ("TOUCH_GRASS_TITLE", alert.title().to_string()),
+        ("TOUCH_GRASS_MESSAGE", alert.body()),
+        ("TOUCH_GRASS_PERCENT", alert.state.percent.round().to_string()),
+        ("TOUCH_GRASS_FATIGUE", alert.state.fatigue.to_string()),
+        ("TOUCH_GRASS_STIMULUS", alert.stimulus.as_str().to_string()),
+        ("TOUCH_GRASS_INTENSITY", alert.intensity.value().to_string()),
+    ]
+}
+
+/// Starts `command` without waiting; a thread reaps it when it exits.
+fn spawn_detached(mut command: Command) -> Result<(), NotifyError> {
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| NotifyError::Failed(e.to_string()))?;
+    thread::spawn(move || {
+        let _ = child.wait();
+    });
+    Ok(())
+}
+
+/// Sends the alert to every `[[webhooks]]` entry subscribed to its event.
+pub struct WebhookNotifier;
+
+impl Notifier for WebhookNotifier {
+    fn notify(&self, app: &AppHandle, alert: &Alert) -> Result<(), NotifyError> {
+        let payload = WebhookPayload {
+            stimulus: Some(alert.stimulus),
+            intensity: Some(alert.intensity),
+            ..WebhookPayload::new(alert.event.webhook_event(), alert.state.clone())
+        };
+        webhooks::dispatch(app, &payload);
+        Ok(())
+    }
+}
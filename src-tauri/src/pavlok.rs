@@ -0,0 +1,288 @@
+use reqwest::blocking::{Client, Response};
+use reqwest::header::RETRY_AFTER;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::Duration;
+
+pub const DEFAULT_API_BASE_URL: &str = "https://api.pavlok.com/api/v5";
+// Points the app at another API host, e.g. the mock server used by the tests.
+pub const API_BASE_URL_ENV: &str = "PAVLOK_API_BASE_URL";
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Stimulus {
+    Beep,
+    Vibe,
+    Zap,
+}
+
+impl Stimulus {
+    /// Maps widget alert modes ("beep", "vibro", "zap") to API stimulus types.
+    pub fn from_mode(mode: &str) -> Self {
+        match mode {
+            "vibro" | "vibration" | "vibe" => Stimulus::Vibe,
+            "zap" => Stimulus::Zap,
+            _ => Stimulus::Beep,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Stimulus::Beep => "beep",
+            Stimulus::Vibe => "vibe",
+            Stimulus::Zap => "zap",
+        }
+    }
+
+    /// Zap starts low: at full strength it is far harsher than beep or vibe.
+    pub fn default_intensity(self) -> Intensity {
+        match self {
+            Stimulus::Beep => Intensity(50),
+            Stimulus::Vibe => Intensity(50),
+            Stimulus::Zap => Intensity(25),
+        }
+    }
+}
+
+/// Stimulus strength as sent in `stimulusValue`; always within the API's 1..=100 range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "u32", into = "u32")]
+pub struct Intensity(u8);
+
+impl Intensity {
+    pub const MIN: u32 = 1;
+    pub const MAX: u32 = 100;
+
+    pub fn new(value: u32) -> Result<Self, String> {
+        if (Self::MIN..=Self::MAX).contains(&value) {
+            Ok(Self(value as u8))
+        } else {
+            Err(format!("intensity must be between {} and {}, got {value}", Self::MIN, Self::MAX))
+        }
+    }
+
+    pub fn value(self) -> u32 {
+        self.0 as u32
+    }
+}
+
+impl TryFrom<u32> for Intensity {
+    type Error = String;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        Self::new(value)
+    }
+}
+
+impl From<Intensity> for u32 {
+    fn from(intensity: Intensity) -> Self {
+        intensity.value()
+    }
+}
+
+/// Failure modes of a Pavlok API call. Serialized as `{ "kind": "...", ... }`
+/// so the widget can react without parsing messages.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub enum PavlokError {
+    /// The API rejected the token (401/403).
+    Unauthorized,
+    /// 429; `retry_after` is in seconds when the server provided it.
+    RateLimited { retry_after: Option<u64> },
+    /// Any other non-success status.
+    Server { status: u16 },
+    /// The request never got a response (DNS, TLS, timeout, ...).
+    Network { message: String },
+    /// The token is empty or cannot be sent as a header.
+    InvalidToken,
+}
+
+impl fmt::Display for PavlokError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PavlokError::Unauthorized => write!(f, "API key rejected"),
+            PavlokError::RateLimited { retry_after: Some(seconds) } => write!(f, "rate limited, retry after {seconds}s"),
+            PavlokError::RateLimited { retry_after: None } => write!(f, "rate limited"),
+            PavlokError::Server { status } => write!(f, "unexpected status {status}"),
+            PavlokError::Network { message } => write!(f, "network error: {message}"),
+            PavlokError::InvalidToken => write!(f, "API key is missing or malformed"),
+        }
+    }
+}
+
+impl std::error::Error for PavlokError {}
+
+/// Accepts both `token` and `Bearer token` and returns the bare token.
+pub fn normalize_token(token: &str) -> Result<&str, PavlokError> {
+    let token_trimmed = token.trim();
+    let mut parts = token_trimmed.split_whitespace();
+    let token_value = match (parts.next(), parts.next()) {
+        (Some(scheme), Some(value)) if scheme.eq_ignore_ascii_case("bearer") => value,
+        _ => token_trimmed,
+    };
+
+    let is_header_safe = token_value.bytes().all(|b| b.is_ascii_graphic());
+    if token_value.is_empty() || !is_header_safe {
+        return Err(PavlokError::InvalidToken);
+    }
+    Ok(token_value)
+}
+
+/// Who a token belongs to, as reported by `GET /user/me`. Fields the API
+/// leaves out stay empty rather than failing the lookup.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountInfo {
+    pub name: Option<String>,
+    pub email: Option<String>,
+    pub devices: Vec<DeviceInfo>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceInfo {
+    pub id: Option<String>,
+    pub name: Option<String>,
+    pub model: Option<String>,
+    pub battery: Option<u8>,
+}
+
+impl AccountInfo {
+    fn from_json(body: &serde_json::Value) -> Self {
+        // Some responses wrap the profile in `user`, others return it bare.
+        let user = body.get("user").unwrap_or(body);
+        let name = text(user, &["name", "displayName", "username"]).or_else(|| {
+            let full = [text(user, &["firstName"]), text(user, &["lastName"])]
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>()
+                .join(" ");
+            Some(full).filter(|full| !full.is_empty())
+        });
+        let devices = match user.get("devices").or_else(|| body.get("devices")) {
+            Some(serde_json::Value::Array(devices)) => devices.iter().map(DeviceInfo::from_json).collect(),
+            Some(device @ serde_json::Value::Object(_)) => vec![DeviceInfo::from_json(device)],
+            _ => user.get("device").map(DeviceInfo::from_json).into_iter().collect(),
+        };
+        Self { name, email: text(user, &["email"]), devices }
+    }
+}
+
+impl DeviceInfo {
+    fn from_json(device: &serde_json::Value) -> Self {
+        Self {
+            id: text(device, &["id", "_id", "serial", "macAddress"]),
+            name: text(device, &["name", "nickname"]),
+            model: text(device, &["model", "type", "deviceType"]),
+            battery: ["battery", "batteryLevel"]
+                .iter()
+                .find_map(|key| device.get(*key).and_then(serde_json::Value::as_u64))
+                .map(|level| level.min(100) as u8),
+        }
+    }
+}
+
+/// First non-empty string (or number) among `keys`.
+fn text(value: &serde_json::Value, keys: &[&str]) -> Option<String> {
+    keys.iter().find_map(|key| match value.get(*key)? {
+        serde_json::Value::String(s) if !s.trim().is_empty() => Some(s.trim().to_string()),
+        serde_json::Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    })
+}
+
+/// Shared HTTP client for the Pavlok API; one instance lives in app state so
+/// connections are pooled across alerts.
+#[derive(Clone)]
+pub struct PavlokClient {
+    http: Client,
+    base_url: String,
+}
+
+impl PavlokClient {
+    pub fn new() -> Self {
+        Self::with_base_url(DEFAULT_API_BASE_URL, DEFAULT_REQUEST_TIMEOUT)
+    }
+
+    /// Uses `PAVLOK_API_BASE_URL` when set, the production API otherwise.
+    pub fn from_env() -> Self {
+        match std::env::var(API_BASE_URL_ENV) {
+            Ok(base_url) if !base_url.trim().is_empty() => Self::with_base_url(base_url.trim(), DEFAULT_REQUEST_TIMEOUT),
+            _ => Self::new(),
+        }
+    }
+
+    pub fn with_base_url(base_url: impl Into<String>, timeout: Duration) -> Self {
+        let http = Client::builder()
+            .timeout(timeout)
+            .build()
+            .expect("failed to build HTTP client");
+        let base_url = base_url.into().trim_end_matches('/').to_string();
+        Self { http, base_url }
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    pub fn send_stimulus(&self, token: &str, stimulus: Stimulus, intensity: Intensity, reason: &str) -> Result<(), PavlokError> {
+        let token_value = normalize_token(token)?;
+        let payload = serde_json::json!({
+            "stimulus": {
+                "stimulusType": stimulus.as_str(),
+                "stimulusValue": intensity.value()
+            },
+            "reason": reason
+        });
+
+        let res = self
+            .http
+            .post(format!("{}/stimulus/send", self.base_url))
+            .bearer_auth(token_value)
+            .json(&payload)
+            .send()
+            .map_err(|e| PavlokError::Network { message: e.to_string() })?;
+
+        check_status(res).map(|_| ())
+    }
+
+    /// Looks up the account behind `token`; a cheap way to find out whether the API accepts it.
+    pub fn fetch_account(&self, token: &str) -> Result<AccountInfo, PavlokError> {
+        let token_value = normalize_token(token)?;
+        let res = self
+            .http
+            .get(format!("{}/user/me", self.base_url))
+            .bearer_auth(token_value)
+            .send()
+            .map_err(|e| PavlokError::Network { message: e.to_string() })?;
+
+        let body: serde_json::Value = check_status(res)?
+            .json()
+            .map_err(|e| PavlokError::Network { message: e.to_string() })?;
+        Ok(AccountInfo::from_json(&body))
+    }
+}
+
+fn check_status(res: Response) -> Result<Response, PavlokError> {
+    match res.status() {
+        status if status.is_success() => Ok(res),
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Err(PavlokError::Unauthorized),
+        StatusCode::TOO_MANY_REQUESTS => {
+            let retry_after = res
+                .headers()
+                .get(RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.trim().parse().ok());
+            Err(PavlokError::RateLimited { retry_after })
+        }
+        status => Err(PavlokError::Server { status: status.as_u16() }),
+    }
+}
+
+impl Default for PavlokClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
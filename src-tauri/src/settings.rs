@@ -0,0 +1,528 @@
+use crate::escalation::{Escalation, EscalationPolicy, EscalationToggle};
+use crate::fatigue::{FatigueEngine, FatigueUpdate, DEFAULT_BREAK_MINUTES, DEFAULT_WORK_MINUTES};
+use crate::history::{History, DEFAULT_RETENTION_DAYS};
+use crate::intensity::{Intensities, IntensityMenu};
+use crate::local_api::{self, LocalApiSettings};
+use crate::monitor;
+use crate::mqtt::{self, MqttSettings};
+use crate::notifier::NotifierSettings;
+use crate::pavlok::Stimulus;
+use crate::snooze::{self, DEFAULT_RESUME_TIME};
+use crate::token_store::TokenStore;
+use crate::tray_controls::TrayControls;
+use crate::webhooks::Webhook;
+use serde::{Deserialize, Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::fs;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager};
+
+pub const SETTINGS_VERSION: u32 = 1;
+pub const SETTINGS_UPDATED_EVENT: &str = "settings-updated";
+// Same bounds as the widget's two-digit inputs.
+pub const WORK_MINUTES_RANGE: RangeInclusive<u32> = 1..=99;
+pub const BREAK_MINUTES_RANGE: RangeInclusive<u32> = 1..=99;
+// Zero keeps history forever.
+pub const HISTORY_RETENTION_DAYS_RANGE: RangeInclusive<u32> = 0..=3650;
+
+// Per-feature files written before settings.toml existed; imported once, then removed.
+const LEGACY_INTENSITY_FILE: &str = "stimulus-intensity.json";
+const LEGACY_ESCALATION_FILE: &str = "escalation.json";
+// Keyring account and fallback file name for the secrets kept out of settings.toml.
+pub const SECRETS_NAME: &str = "settings-secrets";
+
+/// Everything the user can configure, stored as `settings.toml` in app_data_dir.
+/// Window position stays in window-state.json; the API token and the secrets
+/// below go to the keyring and are serialized only as whether they are set.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Settings {
+    pub version: u32,
+    pub work_minutes: u32,
+    pub break_minutes: u32,
+    pub alert_mode: Stimulus,
+    pub history_retention_days: u32,
+    /// Local `HH:MM` at which an "Until Tomorrow" snooze ends.
+    pub snooze_resume_time: String,
+    /// Set once the widget's old localStorage values have been imported.
+    pub legacy_imported: bool,
+    pub intensity: Intensities,
+    pub escalation: EscalationPolicy,
+    pub local_api: LocalApiSettings,
+    pub webhooks: Vec<Webhook>,
+    pub notifiers: NotifierSettings,
+    pub mqtt: MqttSettings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            version: SETTINGS_VERSION,
+            work_minutes: DEFAULT_WORK_MINUTES,
+            break_minutes: DEFAULT_BREAK_MINUTES,
+            alert_mode: Stimulus::Beep,
+            history_retention_days: DEFAULT_RETENTION_DAYS,
+            snooze_resume_time: DEFAULT_RESUME_TIME.to_string(),
+            legacy_imported: false,
+            intensity: Intensities::default(),
+            escalation: EscalationPolicy::default(),
+            local_api: LocalApiSettings::default(),
+            webhooks: Vec::new(),
+            notifiers: NotifierSettings::default(),
+            mqtt: MqttSettings::default(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn validate(&self) -> Result<(), String> {
+        check_range("workMinutes", self.work_minutes, &WORK_MINUTES_RANGE)?;
+        check_range("breakMinutes", self.break_minutes, &BREAK_MINUTES_RANGE)?;
+        check_range("historyRetentionDays", self.history_retention_days, &HISTORY_RETENTION_DAYS_RANGE)?;
+        snooze::parse_resume_time(&self.snooze_resume_time)?;
+        self.escalation.validate()?;
+        self.local_api.validate()?;
+        self.webhooks.iter().try_for_each(Webhook::validate)?;
+        self.notifiers.validate(&self.webhooks)?;
+        self.mqtt.validate()
+    }
+
+    /// Replaces out-of-range values from a hand-edited file with defaults
+    /// rather than refusing to start. A local API token is only generated
+    /// when the secrets were read, so an unreadable one isn't replaced.
+    fn sanitize(&mut self, secrets_loaded: bool) {
+        let defaults = Settings::default();
+        if !WORK_MINUTES_RANGE.contains(&self.work_minutes) {
+            self.work_minutes = defaults.work_minutes;
+        }
+        if !BREAK_MINUTES_RANGE.contains(&self.break_minutes) {
+            self.break_minutes = defaults.break_minutes;
+        }
+        if !HISTORY_RETENTION_DAYS_RANGE.contains(&self.history_retention_days) {
+            self.history_retention_days = defaults.history_retention_days;
+        }
+        if snooze::parse_resume_time(&self.snooze_resume_time).is_err() {
+            self.snooze_resume_time = defaults.snooze_resume_time;
+        }
+        if self.escalation.validate().is_err() {
+            self.escalation = defaults.escalation;
+        }
+        if secrets_loaded {
+            self.local_api.ensure_token();
+        }
+        if self.local_api.validate().is_err() {
+            self.local_api = defaults.local_api;
+        }
+        self.webhooks.retain(|webhook| webhook.validate().is_ok());
+        if self.notifiers.validate(&self.webhooks).is_err() {
+            self.notifiers = defaults.notifiers;
+        }
+        if self.mqtt.validate().is_err() {
+            self.mqtt = defaults.mqtt;
+        }
+    }
+}
+
+fn check_range(name: &str, value: u32, range: &RangeInclusive<u32>) -> Result<(), String> {
+    if range.contains(&value) {
+        Ok(())
+    } else {
+        Err(format!("{name} must be between {} and {}, got {value}", range.start(), range.end()))
+    }
+}
+
+/// For `serialize_with` on a secret: writes whether one is set, never the value.
+pub fn serialize_is_set<S: Serializer>(secret: &str, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_bool(!secret.is_empty())
+}
+
+/// [`serialize_is_set`] for optional secrets.
+pub fn serialize_option_is_set<S: Serializer>(secret: &Option<String>, serializer: S) -> Result<S::Ok, S::Error> {
+    serialize_is_set(secret.as_deref().unwrap_or_default(), serializer)
+}
+
+/// The secrets from [`Settings`], stored through a [`TokenStore`] as one JSON document.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct Secrets {
+    local_api_token: String,
+    /// By webhook URL.
+    webhook_secrets: BTreeMap<String, String>,
+    mqtt_password: Option<String>,
+}
+
+impl Secrets {
+    fn of(settings: &Settings) -> Self {
+        let webhook_secrets = settings
+            .webhooks
+            .iter()
+            .filter_map(|webhook| Some((webhook.url.clone(), webhook.secret.clone().filter(|secret| !secret.is_empty())?)))
+            .collect();
+        Self {
+            local_api_token: settings.local_api.token.clone(),
+            webhook_secrets,
+            mqtt_password: settings.mqtt.password.clone().filter(|password| !password.is_empty()),
+        }
+    }
+
+    /// What `store` holds; an error when it or the JSON in it can't be read.
+    fn load(store: &TokenStore) -> Result<Self, String> {
+        if !store.try_load()? {
+            return Ok(Self::default());
+        }
+        let json = store.token().unwrap_or_default();
+        serde_json::from_str(&json).map_err(|e| e.to_string())
+    }
+
+    /// Fills in what `settings` doesn't carry itself. A value written into the
+    /// file by hand wins, and is moved out of it on the next save.
+    fn fill(self, settings: &mut Settings) {
+        if settings.local_api.token.is_empty() {
+            settings.local_api.token = self.local_api_token;
+        }
+        for webhook in &mut settings.webhooks {
+            if webhook.secret.is_none() {
+                webhook.secret = self.webhook_secrets.get(&webhook.url).cloned();
+            }
+        }
+        if settings.mqtt.password.is_none() {
+            settings.mqtt.password = self.mqtt_password;
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Partial update from `update_settings`; absent fields are left alone.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingsPatch {
+    pub work_minutes: Option<u32>,
+    pub break_minutes: Option<u32>,
+    pub alert_mode: Option<Stimulus>,
+    pub history_retention_days: Option<u32>,
+    pub snooze_resume_time: Option<String>,
+    pub intensity: Option<Intensities>,
+    pub escalation: Option<EscalationPolicy>,
+    pub local_api: Option<LocalApiSettings>,
+    pub webhooks: Option<Vec<Webhook>>,
+    pub notifiers: Option<NotifierSettings>,
+    pub mqtt: Option<MqttSettings>,
+}
+
+impl SettingsPatch {
+    pub fn apply_to(self, settings: &Settings) -> Settings {
+        let mut next = settings.clone();
+        if let Some(work_minutes) = self.work_minutes {
+            next.work_minutes = work_minutes;
+        }
+        if let Some(break_minutes) = self.break_minutes {
+            next.break_minutes = break_minutes;
+        }
+        if let Some(alert_mode) = self.alert_mode {
+            next.alert_mode = alert_mode;
+        }
+        if let Some(days) = self.history_retention_days {
+            next.history_retention_days = days;
+        }
+        if let Some(resume_time) = self.snooze_resume_time {
+            next.snooze_resume_time = resume_time;
+        }
+        if let Some(intensity) = self.intensity {
+            next.intensity = intensity;
+        }
+        if let Some(escalation) = self.escalation {
+            next.escalation = escalation;
+        }
+        if let Some(mut local_api) = self.local_api {
+            // Leaving the token out keeps the current one.
+            if local_api.token.is_empty() {
+                local_api.token = next.local_api.token.clone();
+            }
+            local_api.ensure_token();
+            next.local_api = local_api;
+        }
+        if let Some(mut webhooks) = self.webhooks {
+            for webhook in &mut webhooks {
+                let current = next.webhooks.iter().find(|old| old.url == webhook.url).and_then(|old| old.secret.clone());
+                webhook.secret = keep_secret(webhook.secret.take(), current);
+            }
+            next.webhooks = webhooks;
+        }
+        if let Some(notifiers) = self.notifiers {
+            next.notifiers = notifiers;
+        }
+        if let Some(mut mqtt) = self.mqtt {
+            mqtt.password = keep_secret(mqtt.password.take(), next.mqtt.password.clone());
+            next.mqtt = mqtt;
+        }
+        next
+    }
+}
+
+/// Secrets are never handed out, so a patch without one keeps the current
+/// value; an empty one removes it.
+fn keep_secret(new: Option<String>, current: Option<String>) -> Option<String> {
+    match new {
+        None => current,
+        Some(secret) if secret.is_empty() => None,
+        secret => secret,
+    }
+}
+
+/// The widget's old localStorage keys, passed in once by the frontend.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LegacySettings {
+    pub work_time: Option<String>,
+    pub break_time: Option<String>,
+    pub alert_mode: Option<String>,
+    /// Handed to the token store, never written to settings.toml.
+    pub api_token: Option<String>,
+}
+
+impl LegacySettings {
+    /// Values that don't parse are skipped; numbers are clamped like the widget did.
+    pub fn apply_to(&self, settings: &mut Settings) {
+        let minutes = |raw: &Option<String>, range: &RangeInclusive<u32>| {
+            raw.as_deref()
+                .and_then(|raw| raw.trim().parse::<i64>().ok())
+                .map(|value| value.clamp(*range.start() as i64, *range.end() as i64) as u32)
+        };
+        if let Some(work_minutes) = minutes(&self.work_time, &WORK_MINUTES_RANGE) {
+            settings.work_minutes = work_minutes;
+        }
+        if let Some(break_minutes) = minutes(&self.break_time, &BREAK_MINUTES_RANGE) {
+            settings.break_minutes = break_minutes;
+        }
+        if let Some(mode) = self.alert_mode.as_deref().filter(|mode| ["beep", "vibro", "zap"].contains(mode)) {
+            settings.alert_mode = Stimulus::from_mode(mode);
+        }
+        settings.legacy_imported = true;
+    }
+}
+
+/// Brings an older file up to the current schema, one version at a time.
+fn migrate(mut table: toml::Table) -> toml::Table {
+    let version = table.get("version").and_then(toml::Value::as_integer).unwrap_or(0);
+    if version < 1 {
+        // v0 is the unversioned layout; v1 only adds the version key.
+        table.insert("version".into(), toml::Value::Integer(1));
+    }
+    table
+}
+
+fn parse(raw: &str) -> Result<Settings, String> {
+    let table = raw.parse::<toml::Table>().map_err(|e| e.to_string())?;
+    toml::Value::Table(migrate(table)).try_into::<Settings>().map_err(|e| e.to_string())
+}
+
+pub struct SettingsStore {
+    path: Option<PathBuf>,
+    settings: Settings,
+    /// Where the secrets are saved; without one they only live in memory.
+    secrets: Option<TokenStore>,
+    /// Set when `secrets` couldn't be read. They are then left as they are
+    /// rather than overwritten with the empty values in memory.
+    secrets_failed: bool,
+    /// What went wrong in [`load`](Self::load), for the app to show once started.
+    load_errors: Vec<String>,
+}
+
+impl SettingsStore {
+    pub fn new(path: Option<PathBuf>, settings: Settings) -> Self {
+        Self { path, settings, secrets: None, secrets_failed: false, load_errors: Vec::new() }
+    }
+
+    /// Reads `path`, migrating and sanitizing as needed, and the secrets from
+    /// `secrets`. A missing file starts from defaults plus whatever the older
+    /// per-feature files held. A file that doesn't parse is moved to
+    /// `settings.toml.bak` and the app runs on defaults without writing a new
+    /// one until something changes. Secrets that fail to load leave the local
+    /// API off and nothing is written until something changes.
+    pub fn load(path: Option<PathBuf>, secrets: Option<TokenStore>) -> Self {
+        let mut load_errors = Vec::new();
+        let loaded = secrets.as_ref().map_or(Ok(Secrets::default()), Secrets::load);
+        let secrets_failed = loaded.is_err();
+        let stored = loaded.unwrap_or_else(|error| {
+            load_errors.push(format!("Could not read the settings secrets: {error}"));
+            Secrets::default()
+        });
+        let raw = path.as_ref().and_then(|path| fs::read_to_string(path).ok());
+        let (settings, save) = match raw {
+            Some(raw) => match parse(&raw) {
+                Ok(mut settings) => {
+                    stored.fill(&mut settings);
+                    settings.sanitize(!secrets_failed);
+                    (settings, true)
+                }
+                Err(error) => {
+                    if let Some(path) = &path {
+                        let backup = path.with_extension("toml.bak");
+                        load_errors.push(match fs::rename(path, &backup) {
+                            Ok(()) => format!("{} is not valid ({error}); moved it to {}", path.display(), backup.display()),
+                            Err(e) => format!("{} is not valid ({error}) and could not be moved aside: {e}", path.display()),
+                        });
+                    }
+                    (Settings::default(), false)
+                }
+            },
+            None => {
+                let mut settings = Settings::default();
+                if let Some(dir) = path.as_ref().and_then(|path| path.parent()) {
+                    import_legacy_files(dir, &mut settings);
+                }
+                stored.fill(&mut settings);
+                (settings, true)
+            }
+        };
+        let mut store = Self { path, settings, secrets, secrets_failed, load_errors };
+        if save && !secrets_failed {
+            if let Err(error) = store.save() {
+                store.load_errors.push(error);
+            }
+        }
+        store
+    }
+
+    pub fn get(&self) -> &Settings {
+        &self.settings
+    }
+
+    pub fn load_errors(&self) -> &[String] {
+        &self.load_errors
+    }
+
+    pub fn update(&mut self, settings: Settings) -> Result<(), String> {
+        settings.validate()?;
+        self.settings = settings;
+        self.save()
+    }
+
+    /// Writes settings.toml even when the secrets fail, then reports either error.
+    fn save(&self) -> Result<(), String> {
+        let secrets = self.save_secrets();
+        let Some(path) = &self.path else {
+            return secrets;
+        };
+        let text = toml::to_string_pretty(&self.settings).map_err(|e| e.to_string())?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Could not write {}: {e}", path.display()))?;
+        }
+        fs::write(path, text).map_err(|e| format!("Could not write {}: {e}", path.display()))?;
+        secrets
+    }
+
+    fn save_secrets(&self) -> Result<(), String> {
+        let Some(store) = self.secrets.as_ref().filter(|_| !self.secrets_failed) else {
+            return Ok(());
+        };
+        let secrets = Secrets::of(&self.settings);
+        let json = Some(secrets).filter(|secrets| !secrets.is_empty()).and_then(|secrets| serde_json::to_string(&secrets).ok());
+        // Most saves don't touch a secret; skip the keyring round trip for those.
+        if store.token() == json {
+            return Ok(());
+        }
+        let result = match json {
+            Some(json) => store.save(&json).map(|_| ()),
+            None => store.clear(),
+        };
+        result.map_err(|error| format!("Could not store the settings secrets: {error}"))
+    }
+}
+
+fn import_legacy_files(dir: &Path, settings: &mut Settings) {
+    let read = |name: &str| fs::read_to_string(dir.join(name)).ok();
+    if let Some(intensity) = read(LEGACY_INTENSITY_FILE).and_then(|raw| serde_json::from_str(&raw).ok()) {
+        settings.intensity = intensity;
+    }
+    if let Some(escalation) = read(LEGACY_ESCALATION_FILE)
+        .and_then(|raw| serde_json::from_str::<EscalationPolicy>(&raw).ok())
+        .filter(|policy| policy.validate().is_ok())
+    {
+        settings.escalation = escalation;
+    }
+    for name in [LEGACY_INTENSITY_FILE, LEGACY_ESCALATION_FILE] {
+        let _ = fs::remove_file(dir.join(name));
+    }
+}
+
+pub fn settings_file(app: &AppHandle) -> Option<PathBuf> {
+    let mut dir = app.path().app_data_dir().ok()?;
+    dir.push("settings.toml");
+    Some(dir)
+}
+
+/// Validates and stores a change, then pushes it to the engine, the alert
+/// layer, the tray and the widget.
+pub fn update(app: &AppHandle, patch: SettingsPatch) -> Result<Settings, String> {
+    let settings = {
+        let store = app.state::<Mutex<SettingsStore>>();
+        let mut store = store.lock().unwrap();
+        let next = patch.apply_to(store.get());
+        store.update(next)?;
+        store.get().clone()
+    };
+    apply(app, &settings);
+    let _ = app.emit(SETTINGS_UPDATED_EVENT, &settings);
+    Ok(settings)
+}
+
+/// One-time import of the widget's old localStorage values. Later calls return
+/// the current settings untouched, so a stale browser profile can't overwrite them.
+pub fn import_legacy(app: &AppHandle, legacy: &LegacySettings) -> Result<Settings, String> {
+    let settings = {
+        let store = app.state::<Mutex<SettingsStore>>();
+        let mut store = store.lock().unwrap();
+        if store.get().legacy_imported {
+            return Ok(store.get().clone());
+        }
+        let mut next = store.get().clone();
+        legacy.apply_to(&mut next);
+        store.update(next)?;
+        store.get().clone()
+    };
+    apply(app, &settings);
+    let _ = app.emit(SETTINGS_UPDATED_EVENT, &settings);
+    Ok(settings)
+}
+
+/// Makes the running app match `settings`. Called after every update and once in setup.
+pub fn apply(app: &AppHandle, settings: &Settings) {
+    if let Some(engine) = app.try_state::<Mutex<FatigueEngine>>() {
+        let state = {
+            let mut engine = engine.lock().unwrap();
+            engine.set_limits(settings.work_minutes, settings.break_minutes);
+            engine.snapshot()
+        };
+        monitor::publish(app, &FatigueUpdate { state, events: Vec::new() });
+    }
+    if let Some(history) = app.try_state::<Mutex<History>>() {
+        history.lock().unwrap().set_retention_days(settings.history_retention_days);
+    }
+    if let Some(intensities) = app.try_state::<Mutex<Intensities>>() {
+        *intensities.lock().unwrap() = settings.intensity;
+    }
+    if let Some(menu) = app.try_state::<IntensityMenu>() {
+        menu.sync(&settings.intensity);
+    }
+    if let Some(escalation) = app.try_state::<Mutex<Escalation>>() {
+        let mut escalation = escalation.lock().unwrap();
+        // Replacing the policy restarts the ladder, so only do it on a real change.
+        if *escalation.policy() != settings.escalation {
+            escalation.set_policy(settings.escalation.clone());
+        }
+    }
+    if let Some(toggle) = app.try_state::<EscalationToggle>() {
+        let _ = toggle.0.set_checked(settings.escalation.enabled);
+    }
+    if let Some(controls) = app.try_state::<TrayControls>() {
+        controls.sync_settings(settings);
+    }
+    local_api::configure(app, &settings.local_api);
+    mqtt::configure(app, &settings.mqtt);
+}
@@ -0,0 +1,223 @@
+use crate::alert_queue::now_ms;
+use crate::settings::SettingsStore;
+use chrono::{Local, NaiveTime, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::menu::{MenuItem, PredefinedMenuItem, Submenu};
+use tauri::{AppHandle, Emitter, Manager, Wry};
+
+pub const SNOOZE_UPDATED_EVENT: &str = "snooze-updated";
+pub const MAX_SNOOZE_MINUTES: u32 = 24 * 60;
+/// When "Until Tomorrow" ends unless `snoozeResumeTime` says otherwise.
+pub const DEFAULT_RESUME_TIME: &str = "08:00";
+const MENU_ID_PREFIX: &str = "snooze:";
+const TRAY_MINUTES: [(u32, &str); 3] = [(15, "15 Minutes"), (30, "30 Minutes"), (60, "1 Hour")];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub enum SnoozeDuration {
+    Minutes { minutes: u32 },
+    /// Until the resume time the next morning.
+    UntilTomorrow,
+}
+
+impl SnoozeDuration {
+    /// `resume_at` is the local time of day an `UntilTomorrow` snooze ends.
+    pub fn until_ms(self, now_ms: u64, resume_at: NaiveTime) -> Result<u64, String> {
+        match self {
+            SnoozeDuration::Minutes { minutes } if (1..=MAX_SNOOZE_MINUTES).contains(&minutes) => {
+                Ok(now_ms + minutes as u64 * 60_000)
+            }
+            SnoozeDuration::Minutes { minutes } => {
+                Err(format!("snooze must be between 1 and {MAX_SNOOZE_MINUTES} minutes, got {minutes}"))
+            }
+            SnoozeDuration::UntilTomorrow => Ok(next_resume_ms(now_ms, resume_at)),
+        }
+    }
+}
+
+/// The next time the local clock reads `resume_at`: later today when that is
+/// still ahead (a snooze started after midnight ends that morning), else tomorrow.
+fn next_resume_ms(now_ms: u64, resume_at: NaiveTime) -> u64 {
+    let Some(now) = Local.timestamp_millis_opt(now_ms as i64).single() else {
+        return now_ms + 24 * 60 * 60 * 1000;
+    };
+    let today = now.date_naive();
+    [Some(today), today.succ_opt()]
+        .into_iter()
+        .flatten()
+        .filter_map(|day| day.and_time(resume_at).and_local_timezone(Local).earliest())
+        .map(|resume| resume.timestamp_millis() as u64)
+        .find(|resume| *resume > now_ms)
+        .unwrap_or(now_ms + 24 * 60 * 60 * 1000)
+}
+
+/// Parses `snoozeResumeTime`, a 24-hour `HH:MM`.
+pub fn parse_resume_time(value: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(value, "%H:%M").map_err(|_| format!("snoozeResumeTime must be HH:MM, got {value:?}"))
+}
+
+fn resume_time(app: &AppHandle) -> NaiveTime {
+    let configured = app
+        .try_state::<Mutex<SettingsStore>>()
+        .and_then(|store| parse_resume_time(&store.lock().unwrap().get().snooze_resume_time).ok());
+    configured.unwrap_or_else(|| parse_resume_time(DEFAULT_RESUME_TIME).unwrap())
+}
+
+/// What the widget shows: `until_ms` is set while stimuli are held off.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnoozeState {
+    pub until_ms: Option<u64>,
+}
+
+/// Holds off stimuli until a point in wall-clock time. Fatigue keeps counting;
+/// only sending is skipped. Kept in `snooze.json` so a restart doesn't end it early.
+pub struct Snooze {
+    path: Option<PathBuf>,
+    until_ms: Option<u64>,
+}
+
+impl Snooze {
+    pub fn new(path: Option<PathBuf>) -> Self {
+        Self { path, until_ms: None }
+    }
+
+    pub fn load(path: Option<PathBuf>) -> Self {
+        let state = path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|raw| serde_json::from_str::<SnoozeState>(&raw).ok())
+            .unwrap_or_default();
+        Self { path, until_ms: state.until_ms }
+    }
+
+    pub fn state(&self) -> SnoozeState {
+        SnoozeState { until_ms: self.until_ms }
+    }
+
+    pub fn is_active(&self, now_ms: u64) -> bool {
+        self.until_ms.is_some_and(|until| until > now_ms)
+    }
+
+    /// Whole minutes left, rounded up; `None` when not snoozed.
+    pub fn remaining_minutes(&self, now_ms: u64) -> Option<u64> {
+        self.until_ms.filter(|until| *until > now_ms).map(|until| (until - now_ms).div_ceil(60_000))
+    }
+
+    pub fn snooze_until(&mut self, until_ms: u64) {
+        self.until_ms = Some(until_ms);
+        self.save();
+    }
+
+    pub fn resume(&mut self) {
+        self.until_ms = None;
+        self.save();
+    }
+
+    /// Clears a snooze that has run out; true when that happened.
+    pub fn expire(&mut self, now_ms: u64) -> bool {
+        if self.until_ms.is_some() && !self.is_active(now_ms) {
+            self.resume();
+            return true;
+        }
+        false
+    }
+
+    fn save(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        if let Some(parent) = path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        if let Ok(json) = serde_json::to_string(&self.state()) {
+            let _ = fs::write(path, json);
+        }
+    }
+}
+
+pub fn snooze_file(app: &AppHandle) -> Option<PathBuf> {
+    let mut dir = app.path().app_data_dir().ok()?;
+    dir.push("snooze.json");
+    Some(dir)
+}
+
+/// True while stimuli should not be sent.
+pub fn is_snoozed(app: &AppHandle) -> bool {
+    app.try_state::<Mutex<Snooze>>().is_some_and(|snooze| snooze.lock().unwrap().is_active(now_ms()))
+}
+
+pub fn start(app: &AppHandle, duration: SnoozeDuration) -> Result<SnoozeState, String> {
+    let until_ms = duration.until_ms(now_ms(), resume_time(app))?;
+    let state = {
+        let snooze = app.state::<Mutex<Snooze>>();
+        let mut snooze = snooze.lock().unwrap();
+        snooze.snooze_until(until_ms);
+        snooze.state()
+    };
+    let _ = app.emit(SNOOZE_UPDATED_EVENT, state);
+    Ok(state)
+}
+
+pub fn resume(app: &AppHandle) -> SnoozeState {
+    let state = {
+        let snooze = app.state::<Mutex<Snooze>>();
+        let mut snooze = snooze.lock().unwrap();
+        snooze.resume();
+        snooze.state()
+    };
+    let _ = app.emit(SNOOZE_UPDATED_EVENT, state);
+    state
+}
+
+/// Called by the sampler every second; ends the snooze once its time is up.
+pub fn tick(app: &AppHandle) {
+    let Some(snooze) = app.try_state::<Mutex<Snooze>>() else {
+        return;
+    };
+    let expired = snooze.lock().unwrap().expire(now_ms());
+    if expired {
+        let _ = app.emit(SNOOZE_UPDATED_EVENT, SnoozeState::default());
+    }
+}
+
+/// A click in the tray Snooze submenu.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnoozeMenuAction {
+    Snooze(SnoozeDuration),
+    Resume,
+}
+
+pub fn build_menu<M: Manager<Wry>>(manager: &M) -> tauri::Result<Submenu<Wry>> {
+    let submenu = Submenu::new(manager, "Snooze Alerts", true)?;
+    for (minutes, label) in TRAY_MINUTES {
+        submenu.append(&MenuItem::with_id(manager, format!("{MENU_ID_PREFIX}{minutes}"), label, true, None::<&str>)?)?;
+    }
+    submenu.append(&MenuItem::with_id(manager, format!("{MENU_ID_PREFIX}tomorrow"), "Until Tomorrow", true, None::<&str>)?)?;
+    submenu.append(&PredefinedMenuItem::separator(manager)?)?;
+    submenu.append(&MenuItem::with_id(manager, format!("{MENU_ID_PREFIX}resume"), "Resume Alerts", true, None::<&str>)?)?;
+    Ok(submenu)
+}
+
+/// Parses ids produced by [`build_menu`].
+pub fn parse_menu_id(id: &str) -> Option<SnoozeMenuAction> {
+    match id.strip_prefix(MENU_ID_PREFIX)? {
+        "tomorrow" => Some(SnoozeMenuAction::Snooze(SnoozeDuration::UntilTomorrow)),
+        "resume" => Some(SnoozeMenuAction::Resume),
+        minutes => Some(SnoozeMenuAction::Snooze(SnoozeDuration::Minutes { minutes: minutes.parse().ok()? })),
+    }
+}
+
+pub fn handle_menu(app: &AppHandle, action: SnoozeMenuAction) {
+    match action {
+        SnoozeMenuAction::Snooze(duration) => {
+            let _ = start(app, duration);
+        }
+        SnoozeMenuAction::Resume => {
+            resume(app);
+        }
+    }
+}
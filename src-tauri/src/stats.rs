@@ -0,0 +1,107 @@
+use crate::history::HistoryRecord;
+use crate::pavlok::Stimulus;
+use serde::{Deserialize, Serialize};
+
+// Minute records further apart than this were separated by downtime, which counts as rest.
+const MAX_MINUTE_GAP_MS: u64 = 90_000;
+
+/// Window for `get_stats` and `export_history`, `from_ms <= at < to_ms`. The widget
+/// works out local day and week boundaries, since those depend on the user's time zone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatsRange {
+    pub from_ms: u64,
+    pub to_ms: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StimulusCounts {
+    pub beep: u32,
+    pub vibe: u32,
+    pub zap: u32,
+}
+
+impl StimulusCounts {
+    fn add(&mut self, stimulus: Stimulus) {
+        match stimulus {
+            Stimulus::Beep => self.beep += 1,
+            Stimulus::Vibe => self.vibe += 1,
+            Stimulus::Zap => self.zap += 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Stats {
+    pub range: StatsRange,
+    /// Minutes that counted as work, whether or not monitoring was on.
+    pub active_minutes: u32,
+    pub longest_stretch_minutes: u32,
+    /// Times fatigue climbed to 100%.
+    pub limit_hits: u32,
+    /// Stimuli the API accepted.
+    pub stimuli: StimulusCounts,
+    pub failed_stimuli: u32,
+    /// Share of limit hits followed by a full break (fatigue back to 0) within
+    /// the range, 0-100. `None` when the limit was never hit.
+    pub break_compliance: Option<f64>,
+}
+
+/// Folds history records (oldest first) into the numbers for `range`.
+pub fn compute(range: StatsRange, records: &[HistoryRecord]) -> Stats {
+    let mut stats = Stats {
+        range,
+        active_minutes: 0,
+        longest_stretch_minutes: 0,
+        limit_hits: 0,
+        stimuli: StimulusCounts::default(),
+        failed_stimuli: 0,
+        break_compliance: None,
+    };
+    let mut stretch = 0;
+    let mut previous_at: Option<u64> = None;
+    let mut was_at_limit = false;
+    let mut awaiting_break = false;
+    let mut breaks_taken = 0;
+
+    for record in records.iter().filter(|record| (range.from_ms..range.to_ms).contains(&record.at_ms())) {
+        match record {
+            HistoryRecord::Minute { at_ms, sample } => {
+                if previous_at.is_some_and(|previous| at_ms.saturating_sub(previous) > MAX_MINUTE_GAP_MS) {
+                    stretch = 0;
+                }
+                previous_at = Some(*at_ms);
+
+                if sample.active {
+                    stats.active_minutes += 1;
+                    stretch += 1;
+                    stats.longest_stretch_minutes = stats.longest_stretch_minutes.max(stretch);
+                } else {
+                    stretch = 0;
+                }
+
+                let at_limit = sample.fatigue >= sample.work_minutes;
+                if at_limit && !was_at_limit {
+                    stats.limit_hits += 1;
+                    awaiting_break = true;
+                }
+                if awaiting_break && sample.fatigue == 0 {
+                    breaks_taken += 1;
+                    awaiting_break = false;
+                }
+                was_at_limit = at_limit;
+            }
+            HistoryRecord::Stimulus { stimulus, error, .. } => match error {
+                None => stats.stimuli.add(*stimulus),
+                Some(_) => stats.failed_stimuli += 1,
+            },
+        }
+    }
+
+    if stats.limit_hits > 0 {
+        stats.break_compliance = Some(breaks_taken as f64 / stats.limit_hits as f64 * 100.0);
+    }
+    stats
+}
@@ -0,0 +1,252 @@
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use serde::Serialize;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+const KEYRING_SERVICE: &str = "com.afaustov.pavlok-touch-grass";
+const KEYRING_ACCOUNT: &str = "pavlok-api-token";
+// The fallback keeps the API token in `api-token.enc`, sealed with `api-token.key`.
+const FILE_NAME: &str = "api-token";
+const NONCE_LEN: usize = 12;
+
+/// Where a saved token ended up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TokenStorage {
+    Keyring,
+    EncryptedFile,
+}
+
+pub trait SecretStore: Send + Sync {
+    fn save(&self, token: &str) -> Result<(), String>;
+    fn load(&self) -> Result<Option<String>, String>;
+    fn clear(&self) -> Result<(), String>;
+}
+
+/// Secret Service on Linux, Credential Manager on Windows, Keychain on macOS.
+pub struct KeyringStore {
+    account: &'static str,
+}
+
+impl KeyringStore {
+    pub fn new(account: &'static str) -> Self {
+        Self { account }
+    }
+
+    fn entry(&self) -> Result<keyring::Entry, String> {
+        keyring::Entry::new(KEYRING_SERVICE, self.account).map_err(|e| e.to_string())
+    }
+}
+
+impl SecretStore for KeyringStore {
+    fn save(&self, token: &str) -> Result<(), String> {
+        self.entry()?.set_password(token).map_err(|e| e.to_string())
+    }
+
+    fn load(&self) -> Result<Option<String>, String> {
+        match self.entry()?.get_password() {
+            Ok(token) => Ok(Some(token)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    fn clear(&self) -> Result<(), String> {
+        match self.entry()?.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+}
+
+/// Fallback for systems without a usable keyring (no Secret Service daemon,
+/// locked-down Credential Manager). The token is sealed with ChaCha20-Poly1305
+/// under a random per-install key. Both files are owner-only, but the key sits
+/// next to the ciphertext, so this only keeps the token out of plain sight: it
+/// does not protect it from anyone who can read the app data folder.
+pub struct EncryptedFileStore {
+    dir: PathBuf,
+    name: &'static str,
+}
+
+impl EncryptedFileStore {
+    /// The API token's files in `dir`.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self::named(dir, FILE_NAME)
+    }
+
+    /// `<name>.enc` and `<name>.key` in `dir`.
+    pub fn named(dir: impl Into<PathBuf>, name: &'static str) -> Self {
+        Self { dir: dir.into(), name }
+    }
+
+    fn token_file(&self) -> PathBuf {
+        self.dir.join(format!("{}.enc", self.name))
+    }
+
+    fn key_file(&self) -> PathBuf {
+        self.dir.join(format!("{}.key", self.name))
+    }
+
+    fn cipher(&self, create: bool) -> Result<Option<ChaCha20Poly1305>, String> {
+        let path = self.key_file();
+        match fs::read(&path) {
+            Ok(key) if key.len() == 32 => Ok(Some(ChaCha20Poly1305::new(Key::from_slice(&key)))),
+            Ok(_) if !create => Err("token key file is corrupt".to_string()),
+            Err(_) if !create => Ok(None),
+            _ => {
+                let key = ChaCha20Poly1305::generate_key(&mut OsRng);
+                write_private(&path, &key)?;
+                Ok(Some(ChaCha20Poly1305::new(&key)))
+            }
+        }
+    }
+}
+
+impl SecretStore for EncryptedFileStore {
+    fn save(&self, token: &str) -> Result<(), String> {
+        let cipher = self.cipher(true)?.ok_or("token key unavailable")?;
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let sealed = cipher.encrypt(&nonce, token.as_bytes()).map_err(|e| e.to_string())?;
+        let mut contents = nonce.to_vec();
+        contents.extend_from_slice(&sealed);
+        write_private(&self.token_file(), &contents)
+    }
+
+    fn load(&self) -> Result<Option<String>, String> {
+        let Ok(contents) = fs::read(self.token_file()) else {
+            return Ok(None);
+        };
+        let Some(cipher) = self.cipher(false)? else {
+            return Ok(None);
+        };
+        if contents.len() <= NONCE_LEN {
+            return Err("stored token is corrupt".to_string());
+        }
+        let (nonce, sealed) = contents.split_at(NONCE_LEN);
+        let token = cipher
+            .decrypt(Nonce::from_slice(nonce), sealed)
+            .map_err(|_| "stored token could not be decrypted".to_string())?;
+        String::from_utf8(token).map(Some).map_err(|e| e.to_string())
+    }
+
+    fn clear(&self) -> Result<(), String> {
+        for path in [self.token_file(), self.key_file()] {
+            match fs::remove_file(path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.to_string()),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+fn write_private(path: &Path, contents: &[u8]) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path).map_err(|e| e.to_string())?;
+    // `mode` only applies to new files; tighten one left behind with looser bits.
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(fs::Permissions::from_mode(0o600)).map_err(|e| e.to_string())?;
+    }
+    file.write_all(contents).map_err(|e| e.to_string())
+}
+
+/// Keeps the API token out of the webview: it is persisted through the OS
+/// keyring when possible, the encrypted file otherwise, and cached in memory
+/// for the alert path.
+pub struct TokenStore {
+    keyring: Box<dyn SecretStore>,
+    fallback: Option<Box<dyn SecretStore>>,
+    current: Mutex<Option<String>>,
+}
+
+impl TokenStore {
+    pub fn new(keyring: Box<dyn SecretStore>, fallback: Option<Box<dyn SecretStore>>) -> Self {
+        Self { keyring, fallback, current: Mutex::new(None) }
+    }
+
+    pub fn for_app(app: &AppHandle) -> Self {
+        Self::named(app, KEYRING_ACCOUNT, FILE_NAME)
+    }
+
+    /// Another secret kept like the API token, under its own keyring account
+    /// and fallback file name.
+    pub fn named(app: &AppHandle, account: &'static str, file_name: &'static str) -> Self {
+        let fallback = app
+            .path()
+            .app_data_dir()
+            .ok()
+            .map(|dir| Box::new(EncryptedFileStore::named(dir, file_name)) as Box<dyn SecretStore>);
+        Self::new(Box::new(KeyringStore::new(account)), fallback)
+    }
+
+    pub fn token(&self) -> Option<String> {
+        self.current.lock().unwrap().clone()
+    }
+
+    pub fn save(&self, token: &str) -> Result<TokenStorage, String> {
+        let token = token.trim();
+        if token.is_empty() {
+            return Err("API key is empty".to_string());
+        }
+        let storage = match self.keyring.save(token) {
+            Ok(()) => {
+                // Don't leave an older copy behind in the weaker store.
+                if let Some(fallback) = &self.fallback {
+                    let _ = fallback.clear();
+                }
+                TokenStorage::Keyring
+            }
+            Err(keyring_error) => {
+                let fallback = self.fallback.as_ref().ok_or(keyring_error)?;
+                fallback.save(token)?;
+                TokenStorage::EncryptedFile
+            }
+        };
+        *self.current.lock().unwrap() = Some(token.to_string());
+        Ok(storage)
+    }
+
+    /// Reads the saved token into memory; returns whether there is one.
+    pub fn load(&self) -> bool {
+        self.try_load().unwrap_or(false)
+    }
+
+    /// Like [`load`](Self::load), but an error when the store that would hold
+    /// the token can't be read: the fallback file, or the keyring when there
+    /// is no fallback.
+    pub fn try_load(&self) -> Result<bool, String> {
+        let token = match (self.keyring.load(), &self.fallback) {
+            (Ok(Some(token)), _) => Ok(Some(token)),
+            (_, Some(fallback)) => fallback.load(),
+            (Ok(None), None) => Ok(None),
+            (Err(error), None) => Err(error),
+        };
+        *self.current.lock().unwrap() = token.clone().ok().flatten();
+        token.map(|token| token.is_some())
+    }
+
+    pub fn clear(&self) -> Result<(), String> {
+        *self.current.lock().unwrap() = None;
+        let keyring = self.keyring.clear();
+        if let Some(fallback) = &self.fallback {
+            fallback.clear()?;
+        }
+        keyring
+    }
+}
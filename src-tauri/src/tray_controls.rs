@@ -0,0 +1,132 @@
+use crate::fatigue::FatigueEngine;
+use crate::monitor;
+use crate::pavlok::Stimulus;
+use crate::settings::{self, Settings, SettingsPatch};
+use std::sync::Mutex;
+use tauri::menu::{CheckMenuItem, Submenu};
+use tauri::{AppHandle, Manager, Wry};
+
+pub const MONITORING_MENU_ID: &str = "monitoring";
+const ALERT_MODE_ID_PREFIX: &str = "alert_mode:";
+const PRESET_ID_PREFIX: &str = "preset:";
+/// WORK/BREAK pairs offered in the tray, in minutes.
+pub const PRESETS: [(u32, u32); 4] = [(25, 5), (45, 5), (50, 10), (90, 15)];
+
+/// A tray click on one of the controls below.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrayAction {
+    ToggleMonitoring,
+    AlertMode(Stimulus),
+    Preset { work_minutes: u32, break_minutes: u32 },
+}
+
+impl TrayAction {
+    /// Parses ids produced by [`TrayControls::build`].
+    pub fn parse_id(id: &str) -> Option<Self> {
+        if id == MONITORING_MENU_ID {
+            return Some(TrayAction::ToggleMonitoring);
+        }
+        if let Some(mode) = id.strip_prefix(ALERT_MODE_ID_PREFIX) {
+            return Some(TrayAction::AlertMode(Stimulus::from_mode(mode)));
+        }
+        let (work, brk) = id.strip_prefix(PRESET_ID_PREFIX)?.split_once(':')?;
+        Some(TrayAction::Preset { work_minutes: work.parse().ok()?, break_minutes: brk.parse().ok()? })
+    }
+}
+
+/// Monitoring toggle, alert-mode radio list and WORK/BREAK presets. The widget
+/// changes the same state, so both sides go through the engine and settings
+/// and the items are re-checked from there. That also undoes the OS unchecking
+/// an already-checked item when it is clicked again.
+pub struct TrayControls {
+    monitoring: CheckMenuItem<Wry>,
+    monitoring_shown: Mutex<bool>,
+    alert_mode: Submenu<Wry>,
+    modes: Vec<(Stimulus, CheckMenuItem<Wry>)>,
+    presets: Submenu<Wry>,
+    preset_items: Vec<((u32, u32), CheckMenuItem<Wry>)>,
+}
+
+impl TrayControls {
+    pub fn build<M: Manager<Wry>>(manager: &M, settings: &Settings, monitoring: bool) -> tauri::Result<Self> {
+        let monitoring_item = CheckMenuItem::with_id(manager, MONITORING_MENU_ID, "Monitoring", true, monitoring, None::<&str>)?;
+
+        let alert_mode = Submenu::new(manager, "Alert Mode", true)?;
+        let mut modes = Vec::new();
+        for (stimulus, label) in [(Stimulus::Beep, "Beep"), (Stimulus::Vibe, "Vibe"), (Stimulus::Zap, "Zap")] {
+            let id = format!("{ALERT_MODE_ID_PREFIX}{}", stimulus.as_str());
+            let item = CheckMenuItem::with_id(manager, id, label, true, settings.alert_mode == stimulus, None::<&str>)?;
+            alert_mode.append(&item)?;
+            modes.push((stimulus, item));
+        }
+
+        let presets = Submenu::new(manager, "Work / Break", true)?;
+        let mut preset_items = Vec::new();
+        for (work, brk) in PRESETS {
+            let id = format!("{PRESET_ID_PREFIX}{work}:{brk}");
+            let checked = (settings.work_minutes, settings.break_minutes) == (work, brk);
+            let item = CheckMenuItem::with_id(manager, id, format!("{work} / {brk} min"), true, checked, None::<&str>)?;
+            presets.append(&item)?;
+            preset_items.push(((work, brk), item));
+        }
+
+        Ok(Self {
+            monitoring: monitoring_item,
+            monitoring_shown: Mutex::new(monitoring),
+            alert_mode,
+            modes,
+            presets,
+            preset_items,
+        })
+    }
+
+    pub fn monitoring_item(&self) -> &CheckMenuItem<Wry> {
+        &self.monitoring
+    }
+
+    pub fn alert_mode_submenu(&self) -> &Submenu<Wry> {
+        &self.alert_mode
+    }
+
+    pub fn presets_submenu(&self) -> &Submenu<Wry> {
+        &self.presets
+    }
+
+    /// Limits set from the widget that match no preset leave every preset unchecked.
+    pub fn sync_settings(&self, settings: &Settings) {
+        for (stimulus, item) in &self.modes {
+            let _ = item.set_checked(settings.alert_mode == *stimulus);
+        }
+        for (preset, item) in &self.preset_items {
+            let _ = item.set_checked((settings.work_minutes, settings.break_minutes) == *preset);
+        }
+    }
+
+    /// Called for every engine update, so the menu is only touched on a change.
+    pub fn sync_monitoring(&self, monitoring: bool) {
+        let mut shown = self.monitoring_shown.lock().unwrap();
+        if *shown != monitoring && self.monitoring.set_checked(monitoring).is_ok() {
+            *shown = monitoring;
+        }
+    }
+}
+
+pub fn handle(app: &AppHandle, action: TrayAction) {
+    match action {
+        TrayAction::ToggleMonitoring => {
+            let monitoring = app.state::<Mutex<FatigueEngine>>().lock().unwrap().snapshot().monitoring;
+            monitor::set_monitoring(app, !monitoring);
+        }
+        TrayAction::AlertMode(stimulus) => {
+            let _ = settings::update(app, SettingsPatch { alert_mode: Some(stimulus), ..SettingsPatch::default() });
+        }
+        TrayAction::Preset { work_minutes, break_minutes } => {
+            let patch = SettingsPatch {
+                work_minutes: Some(work_minutes),
+                break_minutes: Some(break_minutes),
+                ..SettingsPatch::default()
+            };
+            let _ = settings::update(app, patch);
+        }
+    }
+}
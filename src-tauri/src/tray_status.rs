@@ -0,0 +1,131 @@
+use crate::alert_queue::now_ms;
+use crate::fatigue::FatigueSnapshot;
+use crate::snooze::Snooze;
+use std::sync::Mutex;
+use tauri::image::Image;
+use tauri::{AppHandle, Manager};
+
+pub const TRAY_ID: &str = "main";
+pub const ICON_SIZE: u32 = 32;
+
+// The widget's bolt, in its 24x24 SVG viewBox.
+const BOLT: [(f32, f32); 7] = [(7.0, 2.0), (7.0, 13.0), (10.0, 13.0), (10.0, 22.0), (17.0, 10.0), (14.0, 10.0), (14.0, 2.0)];
+const EMPTY_COLOR: [u8; 4] = [255, 255, 255, 90];
+// Samples per pixel side, for smooth edges.
+const SUPERSAMPLE: u32 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IconState {
+    Paused,
+    Active,
+    AtLimit,
+}
+
+impl IconState {
+    pub fn of(state: &FatigueSnapshot) -> Self {
+        if !state.monitoring {
+            IconState::Paused
+        } else if state.at_limit {
+            IconState::AtLimit
+        } else {
+            IconState::Active
+        }
+    }
+
+    pub fn fill_color(self) -> [u8; 4] {
+        match self {
+            IconState::Paused => [158, 158, 158, 255],
+            IconState::Active => [255, 215, 0, 255],
+            IconState::AtLimit => [229, 57, 53, 255],
+        }
+    }
+}
+
+/// Pixel rows of the bolt to fill, counted from the bottom.
+pub fn filled_rows(percent: f64, size: u32) -> u32 {
+    (percent.clamp(0.0, 100.0) / 100.0 * size as f64).round() as u32
+}
+
+/// RGBA pixels of the bolt with its bottom `filled_rows` rows in the state's colour.
+pub fn render(filled_rows: u32, state: IconState, size: u32) -> Vec<u8> {
+    let scale = size as f32 / 24.0;
+    let fill_from = size.saturating_sub(filled_rows);
+    let mut rgba = vec![0; (size * size * 4) as usize];
+    for y in 0..size {
+        let color = if y >= fill_from { state.fill_color() } else { EMPTY_COLOR };
+        for x in 0..size {
+            let mut hits = 0;
+            for sy in 0..SUPERSAMPLE {
+                for sx in 0..SUPERSAMPLE {
+                    let px = (x as f32 + (sx as f32 + 0.5) / SUPERSAMPLE as f32) / scale;
+                    let py = (y as f32 + (sy as f32 + 0.5) / SUPERSAMPLE as f32) / scale;
+                    if inside_bolt(px, py) {
+                        hits += 1;
+                    }
+                }
+            }
+            let coverage = hits as f32 / (SUPERSAMPLE * SUPERSAMPLE) as f32;
+            let offset = ((y * size + x) * 4) as usize;
+            rgba[offset..offset + 3].copy_from_slice(&color[..3]);
+            rgba[offset + 3] = (color[3] as f32 * coverage).round() as u8;
+        }
+    }
+    rgba
+}
+
+fn inside_bolt(x: f32, y: f32) -> bool {
+    // Even-odd ray cast against each edge.
+    let mut inside = false;
+    let mut previous = BOLT[BOLT.len() - 1];
+    for (xi, yi) in BOLT {
+        let (xj, yj) = previous;
+        if (yi > y) != (yj > y) && x < (xj - xi) * (y - yi) / (yj - yi) + xi {
+            inside = !inside;
+        }
+        previous = (xi, yi);
+    }
+    inside
+}
+
+/// `snoozed_minutes` is the time left on a snooze, if one is running.
+pub fn tooltip(state: &FatigueSnapshot, snoozed_minutes: Option<u64>) -> String {
+    let percent = state.percent.round();
+    let status = if !state.monitoring {
+        format!("Fatigue {percent}% — paused")
+    } else if state.at_limit {
+        format!("Fatigue {percent}% — limit reached")
+    } else {
+        format!("Fatigue {percent}% — {} min to limit", state.work_minutes - state.fatigue)
+    };
+    match snoozed_minutes {
+        Some(minutes) => format!("{status}\nAlerts snoozed, {minutes} min left"),
+        None => status,
+    }
+}
+
+/// What the tray shows now, so the once-a-second updates only touch it on a change.
+#[derive(Default)]
+pub struct TrayStatus {
+    icon: Option<(u32, IconState)>,
+    tooltip: String,
+}
+
+pub fn update(app: &AppHandle, state: &FatigueSnapshot) {
+    let (Some(tray), Some(status)) = (app.tray_by_id(TRAY_ID), app.try_state::<Mutex<TrayStatus>>()) else {
+        return;
+    };
+    let mut status = status.lock().unwrap();
+    let icon = (filled_rows(state.percent, ICON_SIZE), IconState::of(state));
+    if status.icon != Some(icon) {
+        let image = Image::new_owned(render(icon.0, icon.1, ICON_SIZE), ICON_SIZE, ICON_SIZE);
+        if tray.set_icon(Some(image)).is_ok() {
+            status.icon = Some(icon);
+        }
+    }
+    let snoozed_minutes = app.try_state::<Mutex<Snooze>>().and_then(|snooze| snooze.lock().unwrap().remaining_minutes(now_ms()));
+    let tooltip = tooltip(state, snoozed_minutes);
+    if status.tooltip != tooltip {
+        let _ = tray.set_tooltip(Some(&tooltip));
+        status.tooltip = tooltip;
+    }
+}
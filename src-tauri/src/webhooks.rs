@@ -0,0 +1,242 @@
+use crate::alert_queue::now_ms;
+use crate::fatigue::{EngineEvent, FatigueEngine, FatigueSnapshot, FatigueUpdate};
+use crate::notifier::{AlertEvent, NotifierKind};
+use crate::pavlok::{Intensity, PavlokError, Stimulus};
+use crate::settings::SettingsStore;
+use hmac::{Hmac, Mac};
+use reqwest::blocking::Client;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::fmt::Write;
+use std::sync::mpsc::{self, Sender};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+pub const EVENT_HEADER: &str = "X-Touch-Grass-Event";
+pub const SIGNATURE_HEADER: &str = "X-Touch-Grass-Signature";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum WebhookEvent {
+    LimitReached,
+    StimulusSent,
+    StimulusFailed,
+    /// Fatigue is back to zero.
+    Rested,
+    MonitoringStarted,
+    MonitoringStopped,
+}
+
+impl WebhookEvent {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            WebhookEvent::LimitReached => "limitReached",
+            WebhookEvent::StimulusSent => "stimulusSent",
+            WebhookEvent::StimulusFailed => "stimulusFailed",
+            WebhookEvent::Rested => "rested",
+            WebhookEvent::MonitoringStarted => "monitoringStarted",
+            WebhookEvent::MonitoringStopped => "monitoringStopped",
+        }
+    }
+}
+
+/// One `[[webhooks]]` entry in settings.toml.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Webhook {
+    pub url: String,
+    /// Empty sends every event.
+    pub events: Vec<WebhookEvent>,
+    /// Request body with `{{placeholder}}`s; without one the JSON payload is sent as is.
+    pub template: Option<String>,
+    /// Signs the body with HMAC-SHA256 in the signature header. Kept in the
+    /// keyring; settings.toml and `get_settings` only show `hasSecret`.
+    #[serde(rename(serialize = "hasSecret"), serialize_with = "crate::settings::serialize_option_is_set")]
+    pub secret: Option<String>,
+}
+
+impl Webhook {
+    pub fn validate(&self) -> Result<(), String> {
+        let url = self.url.trim();
+        if url.starts_with("http://") || url.starts_with("https://") {
+            Ok(())
+        } else {
+            Err(format!("webhook url must start with http:// or https://, got {url:?}"))
+        }
+    }
+
+    pub fn wants(&self, event: WebhookEvent) -> bool {
+        self.events.is_empty() || self.events.contains(&event)
+    }
+}
+
+/// What a webhook receives unless it has a template.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookPayload {
+    pub event: WebhookEvent,
+    pub at_ms: u64,
+    pub state: FatigueSnapshot,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stimulus: Option<Stimulus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub intensity: Option<Intensity>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<PavlokError>,
+}
+
+impl WebhookPayload {
+    pub fn new(event: WebhookEvent, state: FatigueSnapshot) -> Self {
+        Self { event, at_ms: now_ms(), state, stimulus: None, intensity: None, error: None }
+    }
+
+    /// Values for template placeholders. Absent ones become empty strings.
+    fn value(&self, name: &str) -> Option<String> {
+        let state = &self.state;
+        Some(match name {
+            "event" => self.event.as_str().to_string(),
+            "atMs" => self.at_ms.to_string(),
+            "percent" => state.percent.round().to_string(),
+            "fatigue" => state.fatigue.to_string(),
+            "restStreak" => state.rest_streak.to_string(),
+            "monitoring" => state.monitoring.to_string(),
+            "atLimit" => state.at_limit.to_string(),
+            "workMinutes" => state.work_minutes.to_string(),
+            "breakMinutes" => state.break_minutes.to_string(),
+            "stimulus" => self.stimulus.map(|stimulus| stimulus.as_str().to_string()).unwrap_or_default(),
+            "intensity" => self.intensity.map(|intensity| intensity.value().to_string()).unwrap_or_default(),
+            "error" => self.error.as_ref().map(ToString::to_string).unwrap_or_default(),
+            _ => return None,
+        })
+    }
+}
+
+/// The request body for `webhook`. Placeholder values are JSON-escaped so they
+/// can go inside string literals; unknown placeholders are left alone.
+pub fn render_body(webhook: &Webhook, payload: &WebhookPayload) -> String {
+    let Some(template) = &webhook.template else {
+        return serde_json::to_string(payload).unwrap_or_default();
+    };
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template.as_str();
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            rest = &rest[start..];
+            break;
+        };
+        match payload.value(after[..end].trim()) {
+            Some(value) => out.push_str(&json_escape(&value)),
+            None => out.push_str(&rest[start..start + 2 + end + 2]),
+        }
+        rest = &after[end + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn json_escape(value: &str) -> String {
+    let quoted = serde_json::to_string(value).unwrap_or_default();
+    quoted[1..quoted.len() - 1].to_string()
+}
+
+/// `sha256=<hex>` of the body, keyed with the webhook's secret.
+pub fn sign(secret: &str, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes keys of any length");
+    mac.update(body.as_bytes());
+    let mut signature = String::from("sha256=");
+    for byte in mac.finalize().into_bytes() {
+        let _ = write!(signature, "{byte:02x}");
+    }
+    signature
+}
+
+pub fn deliver(client: &Client, webhook: &Webhook, event: WebhookEvent, body: String) -> Result<(), String> {
+    let mut request = client
+        .post(webhook.url.trim())
+        .header("Content-Type", "application/json")
+        .header(EVENT_HEADER, event.as_str());
+    if let Some(secret) = webhook.secret.as_deref().filter(|secret| !secret.is_empty()) {
+        request = request.header(SIGNATURE_HEADER, sign(secret, &body));
+    }
+    let response = request.body(body).send().map_err(|e| e.to_string())?;
+    if response.status().is_success() {
+        Ok(())
+    } else {
+        Err(format!("webhook answered {}", response.status()))
+    }
+}
+
+type Delivery = (Webhook, WebhookEvent, String);
+
+/// Hands requests to one background thread, so a slow endpoint never holds up
+/// the sampler and events arrive in order. Failed deliveries are dropped.
+pub struct WebhookSender(Mutex<Sender<Delivery>>);
+
+pub fn spawn_sender() -> WebhookSender {
+    let (sender, receiver) = mpsc::channel::<Delivery>();
+    thread::spawn(move || {
+        let client = Client::builder().timeout(REQUEST_TIMEOUT).build().expect("failed to build HTTP client");
+        for (webhook, event, body) in receiver {
+            let _ = deliver(&client, &webhook, event, body);
+        }
+    });
+    WebhookSender(Mutex::new(sender))
+}
+
+/// Sends `payload` to every configured webhook that wants its event.
+pub fn dispatch(app: &AppHandle, payload: &WebhookPayload) {
+    let webhooks: Vec<Webhook> = match app.try_state::<Mutex<SettingsStore>>() {
+        Some(store) => store.lock().unwrap().get().webhooks.iter().filter(|webhook| webhook.wants(payload.event)).cloned().collect(),
+        None => return,
+    };
+    for webhook in webhooks {
+        enqueue(app, webhook, payload);
+    }
+}
+
+pub fn enqueue(app: &AppHandle, webhook: Webhook, payload: &WebhookPayload) {
+    if let Some(sender) = app.try_state::<WebhookSender>() {
+        let body = render_body(&webhook, payload);
+        let _ = sender.0.lock().unwrap().send((webhook, payload.event, body));
+    }
+}
+
+/// Limit reached and rested, from `monitor::publish`. Events that have the
+/// webhook notifier selected are left to it, so they aren't sent twice.
+pub fn engine_events(app: &AppHandle, update: &FatigueUpdate) {
+    let notifiers = match app.try_state::<Mutex<SettingsStore>>() {
+        Some(store) => store.lock().unwrap().get().notifiers.clone(),
+        None => return,
+    };
+    for event in &update.events {
+        let event = match event {
+            EngineEvent::LimitReached => AlertEvent::LimitReached,
+            EngineEvent::Rested => AlertEvent::Rested,
+        };
+        if !notifiers.for_event(event).contains(&NotifierKind::Webhook) {
+            dispatch(app, &WebhookPayload::new(event.webhook_event(), update.state.clone()));
+        }
+    }
+}
+
+pub fn monitoring_changed(app: &AppHandle, state: &FatigueSnapshot) {
+    let event = if state.monitoring { WebhookEvent::MonitoringStarted } else { WebhookEvent::MonitoringStopped };
+    dispatch(app, &WebhookPayload::new(event, state.clone()));
+}
+
+pub fn stimulus(app: &AppHandle, stimulus: Stimulus, intensity: Intensity, result: &Result<(), PavlokError>) {
+    let state = app.state::<Mutex<FatigueEngine>>().lock().unwrap().snapshot();
+    let event = if result.is_ok() { WebhookEvent::StimulusSent } else { WebhookEvent::StimulusFailed };
+    let payload = WebhookPayload {
+        stimulus: Some(stimulus),
+        intensity: Some(intensity),
+        error: result.as_ref().err().cloned(),
+        ..WebhookPayload::new(event, state)
+    };
+    dispatch(app, &payload);
+}
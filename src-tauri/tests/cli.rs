@@ -0,0 +1,56 @@
+use pavlok_touch_grass_lib::cli::{parse, CliCommand, CliRequest};
+
+fn args(list: &[&str]) -> Vec<String> {
+    std::iter::once("pavlok-touch-grass").chain(list.iter().copied()).map(String::from).collect()
+}
+
+fn command(list: &[&str]) -> CliCommand {
+    parse(args(list)).unwrap().unwrap().command
+}
+
+#[test]
+fn plain_launch_has_no_request() {
+    assert_eq!(parse(args(&[])), Ok(None));
+    // Flags added by the OS or a launcher are ignored.
+    assert_eq!(parse(args(&["-psn_0_12345"])), Ok(None));
+}
+
+#[test]
+fn each_flag_maps_to_a_command() {
+    assert_eq!(command(&["--start"]), CliCommand::Start);
+    assert_eq!(command(&["--stop"]), CliCommand::Stop);
+    assert_eq!(command(&["--reset"]), CliCommand::Reset);
+    assert_eq!(command(&["--status"]), CliCommand::Status);
+    assert_eq!(command(&["--snooze", "30"]), CliCommand::Snooze { minutes: 30 });
+}
+
+#[test]
+fn json_alone_asks_for_status() {
+    let request = parse(args(&["--json"])).unwrap().unwrap();
+
+    assert_eq!(request.command, CliCommand::Status);
+    assert!(request.json);
+}
+
+#[test]
+fn bad_input_is_rejected() {
+    assert!(parse(args(&["--snooze"])).is_err());
+    assert!(parse(args(&["--snooze", "soon"])).is_err());
+    assert!(parse(args(&["--start", "--stop"])).is_err());
+    assert!(parse(args(&["--status", "--reply-to", "192.168.1.5:4000"])).is_err());
+}
+
+#[test]
+fn relaunch_carries_reply_address_and_nonce() {
+    let request = parse(args(&["--stop", "--json", "--reply-to", "127.0.0.1:4000", "--reply-nonce", "abc"])).unwrap();
+
+    assert_eq!(
+        request,
+        Some(CliRequest {
+            command: CliCommand::Stop,
+            json: true,
+            reply_to: Some("127.0.0.1:4000".parse().unwrap()),
+            nonce: Some("abc".into()),
+        })
+    );
+}
@@ -0,0 +1,85 @@
+use pavlok_touch_grass_lib::escalation::{Escalation, EscalationPolicy, EscalationStep};
+use pavlok_touch_grass_lib::intensity::Intensities;
+use pavlok_touch_grass_lib::pavlok::{Intensity, Stimulus};
+
+fn level(value: u32) -> Intensity {
+    Intensity::new(value).unwrap()
+}
+
+fn enabled() -> Escalation {
+    Escalation::new(EscalationPolicy { enabled: true, ..EscalationPolicy::default() })
+}
+
+fn intensities() -> Intensities {
+    Intensities { beep: level(30), vibe: level(40), zap: level(20) }
+}
+
+#[test]
+fn disabled_policy_uses_widget_mode() {
+    let mut escalation = Escalation::new(EscalationPolicy::default());
+    let alert = escalation.next_alert(Stimulus::Vibe, 30, &intensities());
+    assert_eq!(alert, (Stimulus::Vibe, level(40)));
+}
+
+#[test]
+fn steps_follow_minutes_past_limit() {
+    let mut escalation = enabled();
+    assert_eq!(escalation.next_alert(Stimulus::Beep, 0, &intensities()).0, Stimulus::Beep);
+    assert_eq!(escalation.next_alert(Stimulus::Beep, 5, &intensities()).0, Stimulus::Vibe);
+    assert_eq!(escalation.next_alert(Stimulus::Beep, 12, &intensities()).0, Stimulus::Zap);
+}
+
+#[test]
+fn repeated_alerts_ramp_up_to_ceiling() {
+    let mut escalation = enabled();
+    let levels: Vec<u32> = (0..4)
+        .map(|_| escalation.next_alert(Stimulus::Beep, 1, &intensities()).1.value())
+        .collect();
+    assert_eq!(levels, vec![30, 40, 50, 50]);
+}
+
+#[test]
+fn new_step_restarts_ramp() {
+    let mut escalation = enabled();
+    escalation.next_alert(Stimulus::Beep, 0, &intensities());
+    escalation.next_alert(Stimulus::Beep, 1, &intensities());
+    assert_eq!(escalation.next_alert(Stimulus::Beep, 10, &intensities()), (Stimulus::Zap, level(20)));
+}
+
+#[test]
+fn ladder_does_not_step_down_until_reset() {
+    let mut escalation = enabled();
+    escalation.next_alert(Stimulus::Beep, 6, &intensities());
+    assert_eq!(escalation.next_alert(Stimulus::Beep, 2, &intensities()).0, Stimulus::Vibe);
+
+    escalation.reset();
+    assert_eq!(escalation.next_alert(Stimulus::Beep, 2, &intensities()), (Stimulus::Beep, level(30)));
+}
+
+#[test]
+fn validation_rejects_unordered_steps() {
+    let mut policy = EscalationPolicy::default();
+    assert!(policy.validate().is_ok());
+
+    policy.steps = vec![
+        EscalationStep { stimulus: Stimulus::Beep, after_minutes: 0 },
+        EscalationStep { stimulus: Stimulus::Zap, after_minutes: 0 },
+    ];
+    assert!(policy.validate().is_err());
+
+    policy.steps = vec![EscalationStep { stimulus: Stimulus::Vibe, after_minutes: 3 }];
+    assert!(policy.validate().is_err());
+
+    policy.steps.clear();
+    assert!(policy.validate().is_err());
+}
+
+#[test]
+fn policy_deserializes_with_defaults() {
+    let policy: EscalationPolicy = serde_json::from_str(r#"{"enabled":true,"ceiling":80}"#).unwrap();
+    assert!(policy.enabled);
+    assert_eq!(policy.ceiling, level(80));
+    assert_eq!(policy.steps, EscalationPolicy::default().steps);
+
+    assert!(serde_json::from_str::<EscalationPolicy>(r#"{"ceiling":0}"#).is_err());
+}
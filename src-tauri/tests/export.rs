@@ -0,0 +1,125 @@
+mod support;
+
+use pavlok_touch_grass_lib::export::{check_target, default_file_name, export, utc_timestamp, write_records, ExportFormat};
+use pavlok_touch_grass_lib::fatigue::MinuteSample;
+use pavlok_touch_grass_lib::history::{History, HistoryRecord};
+use pavlok_touch_grass_lib::pavlok::{Intensity, PavlokError, Stimulus};
+use chrono::{Local, TimeZone};
+use pavlok_touch_grass_lib::stats::StatsRange;
+use std::path::Path;
+use std::sync::Mutex;
+use support::temp_dir;
+
+const NOW: u64 = 1_709_210_096_000;
+
+fn local_date(ms: u64) -> String {
+    Local.timestamp_millis_opt(ms as i64).unwrap().format("%Y-%m-%d").to_string()
+}
+
+fn minute(at_ms: u64) -> HistoryRecord {
+    HistoryRecord::Minute {
+        at_ms,
+        sample: MinuteSample {
+            active_seconds: 42,
+            active: true,
+            fatigue: 7,
+            rest_streak: 0,
+            monitoring: true,
+            work_minutes: 45,
+            limit_reached: false,
+            rested: false,
+        },
+    }
+}
+
+fn failed_zap(at_ms: u64) -> HistoryRecord {
+    HistoryRecord::Stimulus {
+        at_ms,
+        stimulus: Stimulus::Zap,
+        intensity: Intensity::new(30).unwrap(),
+        reason: "Fatigue limit, \"late\"".into(),
+        error: Some(PavlokError::Server { status: 503 }),
+        retry: true,
+    }
+}
+
+#[test]
+fn csv_has_one_row_per_record_with_escaping() {
+    let mut out = Vec::new();
+
+    write_records(ExportFormat::Csv, &[minute(0), failed_zap(60_000)], &mut out).unwrap();
+
+    let text = String::from_utf8(out).unwrap();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with("type,atMs,time,"));
+    assert_eq!(lines[1], "minute,0,1970-01-01T00:00:00Z,42,true,7,0,true,45,false,false,,,,,");
+    assert_eq!(
+        lines[2],
+        "stimulus,60000,1970-01-01T00:01:00Z,,,,,,,,,zap,30,\"Fatigue limit, \"\"late\"\"\",unexpected status 503,true"
+    );
+}
+
+#[test]
+fn jsonl_round_trips() {
+    let records = vec![minute(0), failed_zap(60_000)];
+    let mut out = Vec::new();
+
+    write_records(ExportFormat::Jsonl, &records, &mut out).unwrap();
+
+    let parsed: Vec<HistoryRecord> = String::from_utf8(out)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(parsed, records);
+}
+
+#[test]
+fn export_writes_only_the_range() {
+    let dir = temp_dir("export-range");
+    let mut history = History::new(Some(dir.join("history.jsonl")), 0);
+    history.append_all(&[minute(0), minute(60_000), minute(120_000)]);
+    let path = dir.join("out").join("history.jsonl");
+
+    let written = export(&Mutex::new(history), ExportFormat::Jsonl, Some(StatsRange { from_ms: 60_000, to_ms: 120_000 }), &path).unwrap();
+
+    assert_eq!(written, 1);
+    assert_eq!(std::fs::read_to_string(path).unwrap().lines().count(), 1);
+}
+
+#[test]
+fn targets_must_be_absolute_with_the_format_extension() {
+    let dir = temp_dir("export-target");
+
+    assert!(check_target(&dir.join("history.csv"), ExportFormat::Csv).is_ok());
+    assert!(check_target(&dir.join("history.JSONL"), ExportFormat::Jsonl).is_ok());
+    assert!(check_target(Path::new("history.csv"), ExportFormat::Csv).is_err());
+    assert!(check_target(&dir.join("history.csv"), ExportFormat::Jsonl).is_err());
+    assert!(check_target(&dir.join("settings.toml"), ExportFormat::Csv).is_err());
+    assert!(check_target(&dir.join("history"), ExportFormat::Csv).is_err());
+}
+
+#[test]
+fn timestamps_are_utc_and_file_names_local_dates() {
+    assert_eq!(utc_timestamp(NOW), "2024-02-29T12:34:56Z");
+    let dir = temp_dir("export-name");
+    assert_eq!(
+        default_file_name(&dir, ExportFormat::Csv, NOW),
+        dir.join(format!("pavlok-history-{}.csv", local_date(NOW)))
+    );
+}
+
+#[test]
+fn second_export_on_the_same_day_gets_its_own_file() {
+    let dir = temp_dir("export-same-day");
+    let history = Mutex::new(History::new(None, 0));
+    let first = default_file_name(&dir, ExportFormat::Csv, NOW);
+    export(&history, ExportFormat::Csv, None, &first).unwrap();
+
+    let second = default_file_name(&dir, ExportFormat::Csv, NOW + 60_000);
+    export(&history, ExportFormat::Csv, None, &second).unwrap();
+
+    assert_eq!(second, dir.join(format!("pavlok-history-{}-2.csv", local_date(NOW))));
+    assert!(first.exists());
+}
@@ -0,0 +1,258 @@
+use pavlok_touch_grass_lib::fatigue::{Clock, EngineEvent, FatigueEngine, PersistedState};
+use pavlok_touch_grass_lib::idle::IdleSource;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+#[derive(Clone)]
+struct ManualClock(Arc<Mutex<Instant>>);
+
+impl ManualClock {
+    fn advance(&self, seconds: u64) {
+        *self.0.lock().unwrap() += Duration::from_secs(seconds);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        *self.0.lock().unwrap()
+    }
+}
+
+#[derive(Clone)]
+struct ManualIdle(Arc<Mutex<Option<f64>>>);
+
+impl ManualIdle {
+    fn set(&self, idle_seconds: Option<f64>) {
+        *self.0.lock().unwrap() = idle_seconds;
+    }
+}
+
+impl IdleSource for ManualIdle {
+    fn idle_seconds(&self) -> Option<f64> {
+        *self.0.lock().unwrap()
+    }
+}
+
+struct Harness {
+    engine: FatigueEngine,
+    clock: ManualClock,
+    idle: ManualIdle,
+}
+
+impl Harness {
+    fn new(work_minutes: u32, break_minutes: u32) -> Self {
+        let clock = ManualClock(Arc::new(Mutex::new(Instant::now())));
+        let idle = ManualIdle(Arc::new(Mutex::new(Some(0.0))));
+        let mut engine = FatigueEngine::new(Box::new(clock.clone()), Box::new(idle.clone()));
+        engine.set_limits(work_minutes, break_minutes);
+        Self { engine, clock, idle }
+    }
+
+    /// Runs one tick per second for `seconds`, collecting every emitted event.
+    fn run(&mut self, seconds: u32, idle_seconds: Option<f64>) -> Vec<EngineEvent> {
+        self.idle.set(idle_seconds);
+        let mut events = Vec::new();
+        for _ in 0..seconds {
+            self.clock.advance(1);
+            events.extend(self.engine.tick());
+        }
+        events
+    }
+
+    fn active_minutes(&mut self, minutes: u32) -> Vec<EngineEvent> {
+        self.run(minutes * 60, Some(0.0))
+    }
+
+    fn idle_minutes(&mut self, minutes: u32) -> Vec<EngineEvent> {
+        self.run(minutes * 60, Some(300.0))
+    }
+}
+
+#[test]
+fn active_minute_increases_fatigue_while_monitoring() {
+    let mut h = Harness::new(45, 5);
+    h.engine.set_monitoring(true);
+    h.active_minutes(3);
+    assert_eq!(h.engine.snapshot().fatigue, 3);
+}
+
+#[test]
+fn activity_does_not_add_fatigue_while_paused() {
+    let mut h = Harness::new(45, 5);
+    h.active_minutes(3);
+    let state = h.engine.snapshot();
+    assert_eq!(state.fatigue, 0);
+    assert_eq!(state.rest_streak, 0);
+}
+
+#[test]
+fn ten_active_seconds_make_a_minute_active() {
+    let mut h = Harness::new(45, 5);
+    h.engine.set_monitoring(true);
+    h.run(9, Some(0.0));
+    h.run(51, Some(300.0));
+    assert_eq!(h.engine.snapshot().fatigue, 0);
+
+    h.run(10, Some(0.0));
+    h.run(50, Some(300.0));
+    assert_eq!(h.engine.snapshot().fatigue, 1);
+}
+
+#[test]
+fn repeated_start_keeps_the_minute_in_progress() {
+    let mut h = Harness::new(45, 5);
+    h.engine.set_monitoring(true);
+    h.run(9, Some(0.0));
+
+    h.engine.set_monitoring(true);
+    h.run(1, Some(0.0));
+    h.run(50, Some(300.0));
+
+    assert_eq!(h.engine.snapshot().fatigue, 1);
+}
+
+#[test]
+fn idle_minute_decreases_fatigue_and_extends_rest_streak() {
+    let mut h = Harness::new(45, 5);
+    h.engine.set_monitoring(true);
+    h.active_minutes(4);
+    h.idle_minutes(2);
+    let state = h.engine.snapshot();
+    assert_eq!(state.fatigue, 2);
+    assert_eq!(state.rest_streak, 2);
+}
+
+#[test]
+fn break_streak_resets_fatigue_and_reports_rested() {
+    let mut h = Harness::new(45, 3);
+    h.engine.set_monitoring(true);
+    h.active_minutes(10);
+    let events = h.idle_minutes(3);
+    assert_eq!(h.engine.snapshot().fatigue, 0);
+    assert_eq!(events, vec![EngineEvent::Rested]);
+}
+
+#[test]
+fn limit_alert_respects_cooldown() {
+    let mut h = Harness::new(2, 5);
+    h.engine.set_monitoring(true);
+    let events = h.active_minutes(2);
+    assert_eq!(events, vec![EngineEvent::LimitReached]);
+    assert!(h.engine.snapshot().at_limit);
+
+    let events = h.active_minutes(3);
+    assert_eq!(events, vec![EngineEvent::LimitReached; 3]);
+}
+
+#[test]
+fn missed_ticks_count_as_inactive_seconds() {
+    let mut h = Harness::new(45, 5);
+    h.engine.set_monitoring(true);
+    h.active_minutes(2);
+    h.idle.set(Some(0.0));
+    h.clock.advance(120);
+    h.engine.tick();
+    let state = h.engine.snapshot();
+    assert_eq!(state.fatigue, 0);
+    assert_eq!(state.rest_streak, 2);
+}
+
+#[test]
+fn failed_idle_probe_is_treated_as_inactivity() {
+    let mut h = Harness::new(45, 5);
+    h.engine.set_monitoring(true);
+    h.active_minutes(1);
+    h.run(60, None);
+    assert_eq!(h.engine.snapshot().fatigue, 0);
+}
+
+#[test]
+fn reset_clears_state_and_reports_rested_once() {
+    let mut h = Harness::new(45, 5);
+    h.engine.set_monitoring(true);
+    h.active_minutes(5);
+    assert_eq!(h.engine.reset(), vec![EngineEvent::Rested]);
+    assert_eq!(h.engine.snapshot().fatigue, 0);
+    assert!(h.engine.reset().is_empty());
+}
+
+#[test]
+fn percent_follows_work_limit() {
+    let mut h = Harness::new(4, 5);
+    h.engine.set_monitoring(true);
+    h.active_minutes(1);
+    assert_eq!(h.engine.snapshot().percent, 25.0);
+    h.engine.set_limits(2, 5);
+    assert_eq!(h.engine.snapshot().percent, 50.0);
+}
+
+const SAVED_AT_MS: u64 = 1_700_000_000_000;
+
+fn saved(fatigue: u32, monitoring: bool) -> PersistedState {
+    PersistedState { fatigue, rest_streak: 0, monitoring, saved_at_ms: SAVED_AT_MS }
+}
+
+#[test]
+fn short_restart_keeps_most_fatigue() {
+    let mut h = Harness::new(45, 5);
+    h.engine.restore(&saved(40, true), SAVED_AT_MS + 2 * 60_000);
+    let state = h.engine.snapshot();
+    assert_eq!(state.fatigue, 38);
+    assert_eq!(state.rest_streak, 2);
+    assert!(state.monitoring);
+}
+
+#[test]
+fn long_downtime_counts_as_full_rest() {
+    let mut h = Harness::new(45, 5);
+    h.engine.restore(&saved(40, false), SAVED_AT_MS + 8 * 60 * 60_000);
+    let state = h.engine.snapshot();
+    assert_eq!(state.fatigue, 0);
+    assert!(!state.monitoring);
+}
+
+#[test]
+fn clock_going_backwards_is_not_rest() {
+    let mut h = Harness::new(45, 5);
+    h.engine.restore(&saved(40, true), SAVED_AT_MS - 60 * 60_000);
+    assert_eq!(h.engine.snapshot().fatigue, 40);
+}
+
+#[test]
+fn persisted_state_round_trips() {
+    let mut h = Harness::new(45, 5);
+    h.engine.set_monitoring(true);
+    h.active_minutes(3);
+    let state = h.engine.persisted_state(SAVED_AT_MS);
+    assert_eq!(state, PersistedState { fatigue: 3, rest_streak: 0, monitoring: true, saved_at_ms: SAVED_AT_MS });
+
+    let mut restored = Harness::new(45, 5);
+    restored.engine.restore(&state, SAVED_AT_MS + 30_000);
+    assert_eq!(restored.engine.snapshot(), h.engine.snapshot());
+}
+
+#[test]
+fn unseen_rest_reports_rested_once_fatigue_clears() {
+    let mut h = Harness::new(45, 5);
+    h.engine.set_monitoring(true);
+    h.active_minutes(3);
+    assert!(h.engine.apply_rest(2).is_empty());
+    assert_eq!(h.engine.apply_rest(3), vec![EngineEvent::Rested]);
+    assert!(h.engine.apply_rest(0).is_empty());
+}
+
+#[test]
+fn finished_minutes_are_collected_once() {
+    let mut h = Harness::new(2, 5);
+    h.engine.set_monitoring(true);
+
+    h.run(60, Some(0.0));
+    h.run(60, Some(0.0));
+    let minutes = h.engine.take_finished_minutes();
+
+    assert_eq!(minutes.len(), 2);
+    assert!(minutes.iter().all(|minute| minute.active && minute.active_seconds == 60));
+    assert_eq!(minutes[1].fatigue, 2);
+    assert!(minutes[1].limit_reached);
+    assert!(h.engine.take_finished_minutes().is_empty());
+}
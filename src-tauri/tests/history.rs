@@ -0,0 +1,95 @@
+mod support;
+
+use pavlok_touch_grass_lib::fatigue::MinuteSample;
+use pavlok_touch_grass_lib::history::{History, HistoryRecord};
+use pavlok_touch_grass_lib::pavlok::{Intensity, PavlokError, Stimulus};
+use std::path::PathBuf;
+use support::temp_dir;
+
+const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+
+fn temp_file(name: &str) -> PathBuf {
+    temp_dir(name).join("history.jsonl")
+}
+
+fn minute(at_ms: u64, fatigue: u32) -> HistoryRecord {
+    HistoryRecord::Minute {
+        at_ms,
+        sample: MinuteSample {
+            active_seconds: 45,
+            active: true,
+            fatigue,
+            rest_streak: 0,
+            monitoring: true,
+            work_minutes: 45,
+            limit_reached: false,
+            rested: false,
+        },
+    }
+}
+
+#[test]
+fn query_returns_records_in_range() {
+    let mut history = History::new(Some(temp_file("history-range")), 90);
+    let now = 100 * DAY_MS;
+    for offset in 0..5 {
+        history.append(&minute(now + offset * 60_000, offset as u32));
+    }
+
+    let records = history.query(Some(now + 60_000), Some(now + 3 * 60_000));
+
+    assert_eq!(records, vec![minute(now + 60_000, 1), minute(now + 2 * 60_000, 2)]);
+    assert_eq!(history.query(None, None).len(), 5);
+}
+
+#[test]
+fn stimuli_round_trip_with_errors() {
+    let mut history = History::new(Some(temp_file("history-stimuli")), 90);
+    let failed = HistoryRecord::Stimulus {
+        at_ms: DAY_MS,
+        stimulus: Stimulus::Zap,
+        intensity: Intensity::new(40).unwrap(),
+        reason: "Fatigue limit".into(),
+        error: Some(PavlokError::RateLimited { retry_after: Some(3) }),
+        retry: true,
+    };
+
+    history.append(&failed);
+
+    assert_eq!(history.query(None, None), vec![failed]);
+}
+
+#[test]
+fn old_records_are_pruned_by_retention() {
+    let path = temp_file("history-prune");
+    let mut history = History::new(Some(path.clone()), 7);
+    let now = 100 * DAY_MS;
+
+    history.append_all(&[minute(now - 10 * DAY_MS, 1), minute(now - 3 * DAY_MS, 2)]);
+    history.prune(now);
+
+    assert_eq!(history.query(None, None), vec![minute(now - 3 * DAY_MS, 2)]);
+    assert!(!path.with_extension("jsonl.tmp").exists());
+}
+
+#[test]
+fn zero_retention_keeps_everything() {
+    let mut history = History::new(Some(temp_file("history-forever")), 0);
+    let now = 1000 * DAY_MS;
+
+    history.append_all(&[minute(DAY_MS, 1), minute(now, 2)]);
+    history.prune(now);
+
+    assert_eq!(history.query(None, None).len(), 2);
+}
+
+#[test]
+fn malformed_lines_are_skipped() {
+    let path = temp_file("history-garbage");
+    std::fs::write(&path, "not json\n{\"type\":\"unknown\",\"atMs\":1}\n").unwrap();
+    let mut history = History::new(Some(path), 0);
+
+    history.append(&minute(5, 3));
+
+    assert_eq!(history.query(None, None), vec![minute(5, 3)]);
+}
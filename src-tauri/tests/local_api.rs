@@ -0,0 +1,74 @@
+use pavlok_touch_grass_lib::cli::CliCommand;
+use pavlok_touch_grass_lib::local_api::{authorized, route, sse_event, LocalApiSettings, Route};
+use pavlok_touch_grass_lib::settings::{Settings, SettingsPatch};
+
+const TOKEN: &str = "0123456789abcdef0123456789abcdef";
+
+#[test]
+fn routes_need_the_right_method() {
+    assert_eq!(route("GET", "/status", ""), Ok(Route::Status));
+    assert_eq!(route("GET", "/events?token=x", ""), Ok(Route::Events));
+    assert_eq!(route("POST", "/start", ""), Ok(Route::Command(CliCommand::Start)));
+    assert_eq!(route("POST", "/stop/", ""), Ok(Route::Command(CliCommand::Stop)));
+    assert_eq!(route("POST", "/reset", ""), Ok(Route::Command(CliCommand::Reset)));
+
+    assert_eq!(route("GET", "/start", "").unwrap_err().status, 405);
+    assert_eq!(route("POST", "/status", "").unwrap_err().status, 405);
+    assert_eq!(route("GET", "/nope", "").unwrap_err().status, 404);
+}
+
+#[test]
+fn snooze_minutes_come_from_query_or_body() {
+    let snooze = |minutes| Ok(Route::Command(CliCommand::Snooze { minutes }));
+
+    assert_eq!(route("POST", "/snooze?minutes=15", ""), snooze(15));
+    assert_eq!(route("POST", "/snooze", r#"{"minutes": 45}"#), snooze(45));
+    assert_eq!(route("POST", "/snooze", "").unwrap_err().status, 400);
+    assert_eq!(route("POST", "/snooze?minutes=later", "").unwrap_err().status, 400);
+}
+
+#[test]
+fn token_is_accepted_from_header_or_query() {
+    assert!(authorized(Some(&format!("Bearer {TOKEN}")), "/status", TOKEN));
+    assert!(authorized(None, &format!("/events?token={TOKEN}"), TOKEN));
+
+    assert!(!authorized(None, "/status", TOKEN));
+    assert!(!authorized(Some("Bearer wrong"), "/status", TOKEN));
+    assert!(!authorized(Some(TOKEN), "/status", TOKEN));
+    // An unset token never matches, not even an empty one.
+    assert!(!authorized(Some("Bearer "), "/status?token=", ""));
+}
+
+#[test]
+fn events_use_the_sse_format() {
+    assert_eq!(sse_event("status", r#"{"percent":50}"#), "event: status\ndata: {\"percent\":50}\n\n");
+}
+
+#[test]
+fn enabling_generates_a_token_and_keeps_it() {
+    let enable = SettingsPatch {
+        local_api: Some(LocalApiSettings { enabled: true, ..LocalApiSettings::default() }),
+        ..SettingsPatch::default()
+    };
+    let enabled = enable.clone().apply_to(&Settings::default());
+    assert!(enabled.validate().is_ok());
+    assert!(enabled.local_api.token.len() >= 16);
+
+    let again = enable.apply_to(&enabled);
+    assert_eq!(again.local_api.token, enabled.local_api.token);
+}
+
+#[test]
+fn short_tokens_and_low_ports_are_rejected() {
+    let short = LocalApiSettings { enabled: true, token: "secret".into(), ..LocalApiSettings::default() };
+    assert!(short.validate().is_err());
+
+    let low_port = LocalApiSettings { port: 80, ..LocalApiSettings::default() };
+    assert!(low_port.validate().is_err());
+}
+
+#[test]
+fn metrics_route_is_get_only() {
+    assert_eq!(route("GET", "/metrics", ""), Ok(Route::Metrics));
+    assert_eq!(route("POST", "/metrics", "").unwrap_err().status, 405);
+}
@@ -0,0 +1,45 @@
+mod support;
+
+use pavlok_touch_grass_lib::fatigue::FatigueSnapshot;
+use pavlok_touch_grass_lib::metrics::{failure_status, Metrics};
+use pavlok_touch_grass_lib::pavlok::{PavlokError, Stimulus};
+
+fn state() -> FatigueSnapshot {
+    FatigueSnapshot { rest_streak: 2, ..support::snapshot(30, 50) }
+}
+
+#[test]
+fn gauges_follow_the_engine() {
+    let text = Metrics::default().render(&state());
+
+    assert!(text.contains("# TYPE touch_grass_fatigue_percent gauge\ntouch_grass_fatigue_percent 60\n"));
+    assert!(text.contains("\ntouch_grass_rest_streak_minutes 2\n"));
+    assert!(text.contains("\ntouch_grass_monitoring 1\n"));
+    // Types with nothing sent yet are still listed.
+    assert!(text.contains("touch_grass_stimuli_sent_total{type=\"zap\"} 0\n"));
+    assert!(!text.contains("touch_grass_api_failures_total{"));
+}
+
+#[test]
+fn stimuli_and_failures_are_counted() {
+    let mut metrics = Metrics::default();
+    metrics.record_stimulus(Stimulus::Vibe, &Ok(()));
+    metrics.record_stimulus(Stimulus::Vibe, &Ok(()));
+    metrics.record_stimulus(Stimulus::Zap, &Err(PavlokError::RateLimited { retry_after: None }));
+    metrics.record_stimulus(Stimulus::Zap, &Err(PavlokError::Server { status: 503 }));
+    metrics.record_stimulus(Stimulus::Zap, &Err(PavlokError::Server { status: 503 }));
+
+    let text = metrics.render(&state());
+
+    assert!(text.contains("touch_grass_stimuli_sent_total{type=\"vibe\"} 2\n"));
+    assert!(text.contains("touch_grass_stimuli_sent_total{type=\"zap\"} 0\n"));
+    assert!(text.contains("touch_grass_api_failures_total{status=\"429\"} 1\n"));
+    assert!(text.contains("touch_grass_api_failures_total{status=\"503\"} 2\n"));
+}
+
+#[test]
+fn failures_without_a_status_get_a_name() {
+    assert_eq!(failure_status(&PavlokError::Unauthorized), "401");
+    assert_eq!(failure_status(&PavlokError::Network { message: "timed out".into() }), "network");
+    assert_eq!(failure_status(&PavlokError::InvalidToken), "invalid_token");
+}
@@ -0,0 +1,63 @@
+use pavlok_touch_grass_lib::cli::CliCommand;
+use pavlok_touch_grass_lib::mqtt::{discovery_configs, parse_command, MqttSettings};
+
+#[test]
+fn commands_are_case_insensitive() {
+    assert_eq!(parse_command(b"start"), Some(CliCommand::Start));
+    assert_eq!(parse_command(b" STOP\n"), Some(CliCommand::Stop));
+    assert_eq!(parse_command(b"Reset"), Some(CliCommand::Reset));
+    assert_eq!(parse_command(b"zap"), None);
+    assert_eq!(parse_command(&[0xff, 0xfe]), None);
+}
+
+#[test]
+fn topics_hang_off_the_base_topic() {
+    let settings = MqttSettings { base_topic: "home/desk/".into(), ..MqttSettings::default() };
+
+    assert_eq!(settings.state_topic(), "home/desk/state");
+    assert_eq!(settings.command_topic(), "home/desk/command");
+    assert_eq!(settings.availability_topic(), "home/desk/status");
+}
+
+#[test]
+fn discovery_describes_every_entity() {
+    let settings = MqttSettings { client_id: "desk.pc".into(), ..MqttSettings::default() };
+
+    let configs = discovery_configs(&settings);
+
+    let topics: Vec<&str> = configs.iter().map(|(topic, _)| topic.as_str()).collect();
+    assert_eq!(
+        topics,
+        [
+            "homeassistant/sensor/desk_pc/fatigue_percent/config",
+            "homeassistant/sensor/desk_pc/fatigue_minutes/config",
+            "homeassistant/sensor/desk_pc/rest_streak/config",
+            "homeassistant/binary_sensor/desk_pc/at_limit/config",
+            "homeassistant/switch/desk_pc/monitoring/config",
+            "homeassistant/button/desk_pc/reset/config",
+        ]
+    );
+    let switch = &configs[4].1;
+    assert_eq!(switch["unique_id"], "desk_pc_monitoring");
+    assert_eq!(switch["state_topic"], "touch_grass/state");
+    assert_eq!(switch["command_topic"], "touch_grass/command");
+    assert_eq!(switch["payload_on"], "start");
+    assert_eq!(switch["availability_topic"], "touch_grass/status");
+    assert_eq!(switch["device"]["identifiers"][0], "desk_pc");
+    assert_eq!(configs[5].1["payload_press"], "reset");
+}
+
+#[test]
+fn empty_prefix_turns_discovery_off() {
+    let settings = MqttSettings { discovery_prefix: String::new(), ..MqttSettings::default() };
+
+    assert!(discovery_configs(&settings).is_empty());
+}
+
+#[test]
+fn settings_are_checked() {
+    assert!(MqttSettings::default().validate().is_ok());
+    assert!(MqttSettings { enabled: true, host: " ".into(), ..MqttSettings::default() }.validate().is_err());
+    assert!(MqttSettings { base_topic: "touch/#".into(), ..MqttSettings::default() }.validate().is_err());
+    assert!(MqttSettings { client_id: String::new(), ..MqttSettings::default() }.validate().is_err());
+}
@@ -0,0 +1,86 @@
+mod support;
+
+use pavlok_touch_grass_lib::notifier::{script_env, Alert, AlertEvent, NotifierKind, NotifierSettings};
+use pavlok_touch_grass_lib::pavlok::{Intensity, Stimulus};
+use pavlok_touch_grass_lib::webhooks::{Webhook, WebhookEvent};
+use support::snapshot;
+
+fn alert(event: AlertEvent) -> Alert {
+    Alert { event, stimulus: Stimulus::Zap, intensity: Intensity::new(60).unwrap(), state: snapshot(50, 50) }
+}
+
+#[test]
+fn pavlok_is_the_default_for_both_events() {
+    let settings = NotifierSettings::default();
+
+    assert_eq!(settings.for_event(AlertEvent::LimitReached), [NotifierKind::Pavlok]);
+    assert_eq!(settings.for_event(AlertEvent::Rested), [NotifierKind::Pavlok]);
+    assert!(settings.validate(&[]).is_ok());
+}
+
+#[test]
+fn events_pick_their_own_backends() {
+    let settings: NotifierSettings =
+        toml::from_str("limitReached = [\"desktop\", \"sound\"]\nrested = []\n").unwrap();
+
+    assert_eq!(settings.for_event(AlertEvent::LimitReached), [NotifierKind::Desktop, NotifierKind::Sound]);
+    assert!(settings.for_event(AlertEvent::Rested).is_empty());
+    assert!(settings.build(NotifierKind::Sound).is_some());
+}
+
+#[test]
+fn snooze_holds_off_all_but_the_webhook() {
+    let settings = NotifierSettings {
+        limit_reached: vec![NotifierKind::Pavlok, NotifierKind::Desktop, NotifierKind::Webhook],
+        rested: vec![NotifierKind::Sound, NotifierKind::Script],
+        ..NotifierSettings::default()
+    };
+
+    assert_eq!(
+        settings.active_for(AlertEvent::LimitReached, false),
+        [NotifierKind::Pavlok, NotifierKind::Desktop, NotifierKind::Webhook]
+    );
+    assert_eq!(settings.active_for(AlertEvent::LimitReached, true), [NotifierKind::Webhook]);
+    assert_eq!(settings.active_for(AlertEvent::Rested, false), [NotifierKind::Sound, NotifierKind::Script]);
+    assert!(settings.active_for(AlertEvent::Rested, true).is_empty());
+}
+
+#[test]
+fn script_and_webhook_need_their_config() {
+    let script = NotifierSettings { limit_reached: vec![NotifierKind::Script], ..NotifierSettings::default() };
+    assert!(script.validate(&[]).is_err());
+    assert!(script.build(NotifierKind::Script).is_none());
+
+    let webhook = NotifierSettings { rested: vec![NotifierKind::Webhook], ..NotifierSettings::default() };
+    assert!(webhook.validate(&[]).is_err());
+
+    let limits_only = Webhook { events: vec![WebhookEvent::LimitReached], ..Webhook::default() };
+    assert!(webhook.validate(std::slice::from_ref(&limits_only)).is_err());
+
+    let rested = Webhook { events: vec![WebhookEvent::Rested], ..Webhook::default() };
+    assert!(webhook.validate(&[limits_only, rested]).is_ok());
+    assert!(webhook.build(NotifierKind::Webhook).is_some());
+}
+
+#[test]
+fn stimuli_carry_the_reason_of_their_event() {
+    assert_eq!(AlertEvent::LimitReached.reason(), "Fatigue limit");
+    assert_eq!(AlertEvent::Rested.reason(), "Rested");
+}
+
+#[test]
+fn alerts_read_well() {
+    assert_eq!(alert(AlertEvent::LimitReached).body(), "Fatigue is at 100%. Take a 5 minute break.");
+    assert_eq!(alert(AlertEvent::Rested).title(), "Fully rested");
+}
+
+#[test]
+fn scripts_get_the_alert_in_the_environment() {
+    let env = script_env(&alert(AlertEvent::LimitReached));
+    let get = |name: &str| env.iter().find(|(key, _)| *key == name).map(|(_, value)| value.as_str());
+
+    assert_eq!(get("TOUCH_GRASS_EVENT"), Some("limitReached"));
+    assert_eq!(get("TOUCH_GRASS_PERCENT"), Some("100"));
+    assert_eq!(get("TOUCH_GRASS_STIMULUS"), Some("zap"));
+    assert_eq!(get("TOUCH_GRASS_INTENSITY"), Some("60"));
+}
@@ -0,0 +1,211 @@
+mod support;
+
+use pavlok_touch_grass_lib::pavlok::{Intensity, PavlokClient, PavlokError, Stimulus, API_BASE_URL_ENV};
+use std::time::Duration;
+use support::mock_pavlok::{MockPavlok, MockResponse};
+
+const TOKEN: &str = "test-token";
+
+fn full() -> Intensity {
+    Intensity::new(100).unwrap()
+}
+
+fn client_for(mock: &MockPavlok) -> PavlokClient {
+    PavlokClient::with_base_url(mock.base_url(), Duration::from_secs(2))
+}
+
+#[test]
+fn sends_stimulus_with_bearer_token() {
+    let mock = MockPavlok::start();
+    let client = client_for(&mock);
+
+    client.send_stimulus(TOKEN, Stimulus::Zap, full(), "Fatigue limit").unwrap();
+
+    let stimuli = mock.stimuli();
+    assert_eq!(stimuli.len(), 1);
+    assert_eq!(stimuli[0].method, "POST");
+    assert_eq!(stimuli[0].path, "/api/v5/stimulus/send");
+    assert_eq!(stimuli[0].authorization.as_deref(), Some("Bearer test-token"));
+    assert_eq!(stimuli[0].stimulus_type(), Some("zap"));
+    assert_eq!(stimuli[0].stimulus_value(), Some(100));
+    assert_eq!(stimuli[0].body["reason"], "Fatigue limit");
+}
+
+#[test]
+fn sends_requested_intensity() {
+    let mock = MockPavlok::start();
+    let client = client_for(&mock);
+
+    client.send_stimulus(TOKEN, Stimulus::Zap, Stimulus::Zap.default_intensity(), "Fatigue limit").unwrap();
+    client.send_stimulus(TOKEN, Stimulus::Vibe, Intensity::new(1).unwrap(), "Fatigue limit").unwrap();
+
+    let values: Vec<_> = mock.stimuli().iter().map(|r| r.stimulus_value().unwrap()).collect();
+    assert_eq!(values, [25, 1]);
+}
+
+#[test]
+fn intensity_is_limited_to_api_range() {
+    assert!(Intensity::new(0).is_err());
+    assert!(Intensity::new(101).is_err());
+    assert_eq!(Intensity::new(100).unwrap().value(), 100);
+    assert!(serde_json::from_str::<Intensity>("250").is_err());
+}
+
+#[test]
+fn accepts_token_with_bearer_prefix() {
+    let mock = MockPavlok::start();
+    let client = client_for(&mock);
+
+    client.send_stimulus("  bearer test-token ", Stimulus::Beep, full(), "Fatigue limit").unwrap();
+
+    assert_eq!(mock.stimuli()[0].authorization.as_deref(), Some("Bearer test-token"));
+}
+
+#[test]
+fn maps_widget_modes_to_api_stimuli() {
+    let mock = MockPavlok::start();
+    let client = client_for(&mock);
+
+    for mode in ["beep", "vibro", "zap"] {
+        client.send_stimulus(TOKEN, Stimulus::from_mode(mode), full(), "Fatigue limit").unwrap();
+    }
+
+    let sent: Vec<_> = mock.stimuli().iter().map(|r| r.stimulus_type().unwrap().to_string()).collect();
+    assert_eq!(sent, ["beep", "vibe", "zap"]);
+}
+
+#[test]
+fn empty_token_is_rejected_without_a_request() {
+    let mock = MockPavlok::start();
+    let client = client_for(&mock);
+
+    assert_eq!(client.send_stimulus("  ", Stimulus::Beep, full(), "Fatigue limit"), Err(PavlokError::InvalidToken));
+    assert!(mock.requests().is_empty());
+}
+
+#[test]
+fn unauthorized_statuses_map_to_unauthorized() {
+    let mock = MockPavlok::start();
+    let client = client_for(&mock);
+    mock.respond_with([MockResponse::Status(401), MockResponse::Status(403)]);
+
+    assert_eq!(client.send_stimulus(TOKEN, Stimulus::Beep, full(), "Fatigue limit"), Err(PavlokError::Unauthorized));
+    assert_eq!(client.send_stimulus(TOKEN, Stimulus::Beep, full(), "Fatigue limit"), Err(PavlokError::Unauthorized));
+}
+
+#[test]
+fn rate_limit_reports_retry_after() {
+    let mock = MockPavlok::start();
+    let client = client_for(&mock);
+    mock.respond_with([
+        MockResponse::RateLimited { retry_after: Some(30) },
+        MockResponse::RateLimited { retry_after: None },
+    ]);
+
+    assert_eq!(
+        client.send_stimulus(TOKEN, Stimulus::Beep, full(), "Fatigue limit"),
+        Err(PavlokError::RateLimited { retry_after: Some(30) })
+    );
+    assert_eq!(
+        client.send_stimulus(TOKEN, Stimulus::Beep, full(), "Fatigue limit"),
+        Err(PavlokError::RateLimited { retry_after: None })
+    );
+}
+
+#[test]
+fn server_error_reports_status() {
+    let mock = MockPavlok::start();
+    let client = client_for(&mock);
+    mock.respond_with([MockResponse::Status(500)]);
+
+    assert_eq!(
+        client.send_stimulus(TOKEN, Stimulus::Beep, full(), "Fatigue limit"),
+        Err(PavlokError::Server { status: 500 })
+    );
+    client.send_stimulus(TOKEN, Stimulus::Beep, full(), "Fatigue limit").unwrap();
+}
+
+#[test]
+fn timeout_is_a_network_error() {
+    let mock = MockPavlok::start();
+    let client = PavlokClient::with_base_url(mock.base_url(), Duration::from_millis(200));
+    mock.respond_with([MockResponse::Hang(Duration::from_secs(2))]);
+
+    let result = client.send_stimulus(TOKEN, Stimulus::Beep, full(), "Fatigue limit");
+    assert!(matches!(result, Err(PavlokError::Network { .. })), "{result:?}");
+}
+
+#[test]
+fn unreachable_host_is_a_network_error() {
+    let base_url = {
+        let mock = MockPavlok::start();
+        mock.base_url().to_string()
+    };
+    let client = PavlokClient::with_base_url(base_url, Duration::from_secs(2));
+
+    let result = client.send_stimulus(TOKEN, Stimulus::Beep, full(), "Fatigue limit");
+    assert!(matches!(result, Err(PavlokError::Network { .. })), "{result:?}");
+}
+
+#[test]
+fn fetch_account_returns_name_and_devices() {
+    let mock = MockPavlok::start();
+    let client = client_for(&mock);
+
+    let account = client.fetch_account(TOKEN).unwrap();
+
+    let request = &mock.requests()[0];
+    assert_eq!(request.method, "GET");
+    assert_eq!(request.path, "/api/v5/user/me");
+    assert_eq!(request.authorization.as_deref(), Some("Bearer test-token"));
+    assert_eq!(account.name.as_deref(), Some("Mock User"));
+    assert_eq!(account.devices.len(), 1);
+    assert_eq!(account.devices[0].model.as_deref(), Some("pavlok3"));
+    assert_eq!(account.devices[0].battery, Some(87));
+}
+
+#[test]
+fn fetch_account_tolerates_wrapped_and_sparse_profiles() {
+    let mock = MockPavlok::start();
+    mock.set_account(serde_json::json!({
+        "user": { "firstName": "Ada", "lastName": "Lovelace", "device": { "id": 42 } }
+    }));
+    let client = client_for(&mock);
+
+    let account = client.fetch_account(TOKEN).unwrap();
+
+    assert_eq!(account.name.as_deref(), Some("Ada Lovelace"));
+    assert_eq!(account.email, None);
+    assert_eq!(account.devices[0].id.as_deref(), Some("42"));
+}
+
+#[test]
+fn fetch_account_reports_rejected_token() {
+    let mock = MockPavlok::start();
+    mock.respond_with([MockResponse::Status(401)]);
+    let client = client_for(&mock);
+
+    assert_eq!(client.fetch_account(TOKEN), Err(PavlokError::Unauthorized));
+    assert_eq!(client.fetch_account("   "), Err(PavlokError::InvalidToken));
+    assert_eq!(mock.requests().len(), 1);
+}
+
+#[test]
+fn errors_serialize_with_kind_tag() {
+    let json = serde_json::to_value(PavlokError::RateLimited { retry_after: Some(5) }).unwrap();
+    assert_eq!(json, serde_json::json!({ "kind": "rateLimited", "retryAfter": 5 }));
+    let json = serde_json::to_value(PavlokError::Server { status: 502 }).unwrap();
+    assert_eq!(json, serde_json::json!({ "kind": "server", "status": 502 }));
+}
+
+#[test]
+fn base_url_comes_from_environment() {
+    let mock = MockPavlok::start();
+    std::env::set_var(API_BASE_URL_ENV, format!("{}/", mock.base_url()));
+    let client = PavlokClient::from_env();
+    std::env::remove_var(API_BASE_URL_ENV);
+
+    assert_eq!(client.base_url(), mock.base_url());
+    client.send_stimulus(TOKEN, Stimulus::Vibe, full(), "Fatigue limit").unwrap();
+    assert_eq!(mock.stimuli().len(), 1);
+}
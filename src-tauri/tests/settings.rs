@@ -0,0 +1,241 @@
+mod support;
+
+use pavlok_touch_grass_lib::mqtt::MqttSettings;
+use pavlok_touch_grass_lib::settings::{LegacySettings, Settings, SettingsPatch, SettingsStore, SETTINGS_VERSION};
+use pavlok_touch_grass_lib::pavlok::{Intensity, Stimulus};
+use pavlok_touch_grass_lib::token_store::{EncryptedFileStore, SecretStore, TokenStore};
+use pavlok_touch_grass_lib::webhooks::Webhook;
+use std::path::Path;
+use support::temp_dir;
+
+const API_TOKEN: &str = "0123456789abcdef-token";
+const HOOK_URL: &str = "https://example.com/hook";
+
+fn secret_store(dir: &Path) -> Option<TokenStore> {
+    Some(TokenStore::new(Box::new(EncryptedFileStore::named(dir, "secrets")), None))
+}
+
+/// A keyring that is there but can't be read, as while it is locked.
+struct LockedKeyring;
+
+impl SecretStore for LockedKeyring {
+    fn save(&self, _token: &str) -> Result<(), String> {
+        panic!("secrets were overwritten")
+    }
+
+    fn load(&self) -> Result<Option<String>, String> {
+        Err("keyring is locked".into())
+    }
+
+    fn clear(&self) -> Result<(), String> {
+        panic!("secrets were cleared")
+    }
+}
+
+fn signed_webhook(url: &str, secret: &str) -> Webhook {
+    Webhook { url: url.into(), secret: Some(secret.into()), ..Webhook::default() }
+}
+
+#[test]
+fn missing_file_is_created_with_defaults() {
+    let path = temp_dir("settings-defaults").join("settings.toml");
+
+    let store = SettingsStore::load(Some(path.clone()), None);
+
+    assert_eq!(store.get(), &Settings::default());
+    let raw = std::fs::read_to_string(&path).unwrap();
+    assert!(raw.contains(&format!("version = {SETTINGS_VERSION}")));
+    assert!(raw.contains("workMinutes = 45"));
+}
+
+#[test]
+fn updates_survive_reload() {
+    let path = temp_dir("settings-reload").join("settings.toml");
+    let mut store = SettingsStore::load(Some(path.clone()), None);
+
+    let patch = SettingsPatch { work_minutes: Some(50), alert_mode: Some(Stimulus::Zap), ..SettingsPatch::default() };
+    store.update(patch.apply_to(store.get())).unwrap();
+
+    let reloaded = SettingsStore::load(Some(path), None);
+    assert_eq!(reloaded.get().work_minutes, 50);
+    assert_eq!(reloaded.get().break_minutes, 5);
+    assert_eq!(reloaded.get().alert_mode, Stimulus::Zap);
+}
+
+#[test]
+fn out_of_range_updates_are_rejected() {
+    let mut store = SettingsStore::new(None, Settings::default());
+
+    for patch in [
+        SettingsPatch { work_minutes: Some(0), ..SettingsPatch::default() },
+        SettingsPatch { break_minutes: Some(100), ..SettingsPatch::default() },
+    ] {
+        assert!(store.update(patch.apply_to(store.get())).is_err());
+    }
+    assert_eq!(store.get(), &Settings::default());
+}
+
+#[test]
+fn unversioned_file_is_migrated_and_sanitized() {
+    let path = temp_dir("settings-migrate").join("settings.toml");
+    std::fs::write(&path, "workMinutes = 30\nbreakMinutes = 500\nalertMode = \"vibe\"\n").unwrap();
+
+    let store = SettingsStore::load(Some(path.clone()), None);
+
+    assert_eq!(store.get().version, SETTINGS_VERSION);
+    assert_eq!(store.get().work_minutes, 30);
+    assert_eq!(store.get().break_minutes, 5);
+    assert_eq!(store.get().alert_mode, Stimulus::Vibe);
+    assert!(std::fs::read_to_string(path).unwrap().contains("version = 1"));
+}
+
+#[test]
+fn unreadable_file_falls_back_to_defaults() {
+    let path = temp_dir("settings-garbage").join("settings.toml");
+    std::fs::write(&path, "workMinutes = [not toml").unwrap();
+
+    assert_eq!(SettingsStore::load(Some(path), None).get(), &Settings::default());
+}
+
+#[test]
+fn unreadable_file_is_moved_aside_not_overwritten() {
+    let path = temp_dir("settings-backup").join("settings.toml");
+    let original = "workMinutes = 30\n[[webhooks]]\nurl = \"https://example.com\"\nevents = [limitReached]\n";
+    std::fs::write(&path, original).unwrap();
+
+    let store = SettingsStore::load(Some(path.clone()), None);
+
+    assert_eq!(store.get(), &Settings::default());
+    assert!(!path.exists());
+    assert_eq!(std::fs::read_to_string(path.with_extension("toml.bak")).unwrap(), original);
+    assert_eq!(store.load_errors().len(), 1);
+    assert!(store.load_errors()[0].contains("settings.toml.bak"));
+}
+
+#[test]
+fn secrets_written_by_hand_move_out_of_the_file() {
+    let dir = temp_dir("settings-secrets");
+    let path = dir.join("settings.toml");
+    std::fs::write(&path, format!("[localApi]\nenabled = true\ntoken = \"{API_TOKEN}\"\n")).unwrap();
+
+    let store = SettingsStore::load(Some(path.clone()), secret_store(&dir));
+
+    assert_eq!(store.get().local_api.token, API_TOKEN);
+    let raw = std::fs::read_to_string(&path).unwrap();
+    assert!(!raw.contains(API_TOKEN));
+    assert!(raw.contains("hasToken = true"));
+    let reloaded = SettingsStore::load(Some(path), secret_store(&dir));
+    assert_eq!(reloaded.get().local_api.token, API_TOKEN);
+}
+
+#[test]
+fn unreadable_secrets_are_left_alone() {
+    let path = temp_dir("settings-locked-secrets").join("settings.toml");
+    std::fs::write(&path, "[localApi]\nenabled = true\nhasToken = true\n").unwrap();
+    let locked = || Some(TokenStore::new(Box::new(LockedKeyring), None));
+
+    let mut store = SettingsStore::load(Some(path.clone()), locked());
+
+    assert!(store.get().local_api.token.is_empty());
+    assert!(!store.get().local_api.enabled);
+    assert!(store.load_errors()[0].contains("keyring is locked"));
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "[localApi]\nenabled = true\nhasToken = true\n");
+    let patch = SettingsPatch { work_minutes: Some(30), ..SettingsPatch::default() };
+    store.update(patch.apply_to(store.get())).unwrap();
+    assert_eq!(store.get().work_minutes, 30);
+}
+
+#[test]
+fn secrets_are_serialized_only_as_being_set() {
+    let mut settings = Settings::default();
+    settings.local_api.token = API_TOKEN.into();
+    settings.webhooks = vec![signed_webhook(HOOK_URL, "hook-secret"), signed_webhook("https://example.com/plain", "")];
+    settings.mqtt.password = Some("broker-password".into());
+
+    let json = serde_json::to_value(&settings).unwrap();
+
+    assert_eq!(json["localApi"]["hasToken"], true);
+    assert_eq!(json["webhooks"][0]["hasSecret"], true);
+    assert_eq!(json["webhooks"][1]["hasSecret"], false);
+    assert!(!json.to_string().contains(API_TOKEN));
+    assert!(!json.to_string().contains("hook-secret"));
+    assert_eq!(json["mqtt"]["hasPassword"], true);
+    assert!(!json.to_string().contains("broker-password"));
+}
+
+#[test]
+fn mqtt_password_is_kept_until_a_new_one_is_given() {
+    let dir = temp_dir("settings-mqtt-password");
+    let path = dir.join("settings.toml");
+    std::fs::write(&path, "[mqtt]\nusername = \"touch-grass\"\npassword = \"broker-password\"\n").unwrap();
+    let mut store = SettingsStore::load(Some(path.clone()), secret_store(&dir));
+    assert!(!std::fs::read_to_string(&path).unwrap().contains("broker-password"));
+
+    let mqtt = MqttSettings { password: None, ..store.get().mqtt.clone() };
+    let patch = SettingsPatch { mqtt: Some(mqtt), ..SettingsPatch::default() };
+    store.update(patch.apply_to(store.get())).unwrap();
+    let reloaded = SettingsStore::load(Some(path.clone()), secret_store(&dir));
+    assert_eq!(reloaded.get().mqtt.password.as_deref(), Some("broker-password"));
+
+    let mqtt = MqttSettings { password: Some("rotated".into()), ..store.get().mqtt.clone() };
+    let patch = SettingsPatch { mqtt: Some(mqtt), ..SettingsPatch::default() };
+    store.update(patch.apply_to(store.get())).unwrap();
+    let reloaded = SettingsStore::load(Some(path), secret_store(&dir));
+    assert_eq!(reloaded.get().mqtt.password.as_deref(), Some("rotated"));
+}
+
+#[test]
+fn webhook_secrets_survive_reload_and_only_change_when_given() {
+    let dir = temp_dir("settings-webhook-secrets");
+    let path = dir.join("settings.toml");
+    let mut store = SettingsStore::load(Some(path.clone()), secret_store(&dir));
+    let patch = SettingsPatch { webhooks: Some(vec![signed_webhook(HOOK_URL, "hook-secret")]), ..SettingsPatch::default() };
+    store.update(patch.apply_to(store.get())).unwrap();
+
+    let mut store = SettingsStore::load(Some(path.clone()), secret_store(&dir));
+    assert_eq!(store.get().webhooks[0].secret.as_deref(), Some("hook-secret"));
+    assert!(!std::fs::read_to_string(&path).unwrap().contains("hook-secret"));
+
+    // The widget sends webhooks back without their secrets.
+    let unchanged = Webhook { url: HOOK_URL.into(), ..Webhook::default() };
+    let patch = SettingsPatch { webhooks: Some(vec![unchanged]), ..SettingsPatch::default() };
+    store.update(patch.apply_to(store.get())).unwrap();
+    assert_eq!(store.get().webhooks[0].secret.as_deref(), Some("hook-secret"));
+
+    let patch = SettingsPatch { webhooks: Some(vec![signed_webhook(HOOK_URL, "")]), ..SettingsPatch::default() };
+    store.update(patch.apply_to(store.get())).unwrap();
+    assert_eq!(store.get().webhooks[0].secret, None);
+}
+
+#[test]
+fn older_per_feature_files_are_imported_once() {
+    let dir = temp_dir("settings-legacy-files");
+    std::fs::write(dir.join("stimulus-intensity.json"), r#"{"beep":10,"vibe":20,"zap":30}"#).unwrap();
+    std::fs::write(dir.join("escalation.json"), r#"{"enabled":true}"#).unwrap();
+
+    let store = SettingsStore::load(Some(dir.join("settings.toml")), None);
+
+    assert_eq!(store.get().intensity.zap, Intensity::new(30).unwrap());
+    assert!(store.get().escalation.enabled);
+    assert!(!dir.join("stimulus-intensity.json").exists());
+    assert!(!dir.join("escalation.json").exists());
+}
+
+#[test]
+fn legacy_local_storage_values_are_clamped() {
+    let mut settings = Settings::default();
+    let legacy = LegacySettings {
+        work_time: Some("250".into()),
+        break_time: Some("abc".into()),
+        alert_mode: Some("vibro".into()),
+        api_token: Some("secret".into()),
+    };
+
+    legacy.apply_to(&mut settings);
+
+    assert_eq!(settings.work_minutes, 99);
+    assert_eq!(settings.break_minutes, 5);
+    assert_eq!(settings.alert_mode, Stimulus::Vibe);
+    assert!(settings.legacy_imported);
+    assert!(!toml::to_string(&settings).unwrap().contains("secret"));
+}
@@ -0,0 +1,108 @@
+mod support;
+
+use chrono::{Local, NaiveDate, NaiveTime};
+use pavlok_touch_grass_lib::snooze::{
+    parse_menu_id, parse_resume_time, Snooze, SnoozeDuration, SnoozeMenuAction, SnoozeState, DEFAULT_RESUME_TIME,
+};
+use std::path::PathBuf;
+use support::temp_dir;
+
+const NOW: u64 = 1_700_000_000_000;
+
+fn temp_file(name: &str) -> PathBuf {
+    temp_dir(name).join("snooze.json")
+}
+
+fn time(hour: u32, minute: u32) -> NaiveTime {
+    NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+}
+
+/// Milliseconds for a local wall-clock time on a day without a DST change.
+fn local_ms(day: u32, hour: u32, minute: u32) -> u64 {
+    let date = NaiveDate::from_ymd_opt(2026, 6, day).unwrap();
+    date.and_time(time(hour, minute)).and_local_timezone(Local).earliest().unwrap().timestamp_millis() as u64
+}
+
+#[test]
+fn minutes_are_validated() {
+    let resume_at = time(8, 0);
+    assert_eq!(SnoozeDuration::Minutes { minutes: 30 }.until_ms(NOW, resume_at), Ok(NOW + 30 * 60_000));
+    assert!(SnoozeDuration::Minutes { minutes: 0 }.until_ms(NOW, resume_at).is_err());
+    assert!(SnoozeDuration::Minutes { minutes: 24 * 60 + 1 }.until_ms(NOW, resume_at).is_err());
+}
+
+#[test]
+fn until_tomorrow_started_late_lasts_the_night() {
+    let until = SnoozeDuration::UntilTomorrow.until_ms(local_ms(10, 23, 55), time(8, 0)).unwrap();
+
+    assert_eq!(until, local_ms(11, 8, 0));
+}
+
+#[test]
+fn until_tomorrow_started_after_midnight_ends_that_morning() {
+    let until = SnoozeDuration::UntilTomorrow.until_ms(local_ms(11, 1, 30), time(8, 0)).unwrap();
+
+    assert_eq!(until, local_ms(11, 8, 0));
+}
+
+#[test]
+fn until_tomorrow_uses_the_configured_resume_time() {
+    let until = SnoozeDuration::UntilTomorrow.until_ms(local_ms(10, 18, 0), time(6, 30)).unwrap();
+
+    assert_eq!(until, local_ms(11, 6, 30));
+}
+
+#[test]
+fn resume_time_is_hours_and_minutes() {
+    assert_eq!(parse_resume_time(DEFAULT_RESUME_TIME), Ok(time(8, 0)));
+    assert_eq!(parse_resume_time("06:30"), Ok(time(6, 30)));
+    assert!(parse_resume_time("25:00").is_err());
+    assert!(parse_resume_time("8am").is_err());
+}
+
+#[test]
+fn snooze_runs_out_and_reports_once() {
+    let mut snooze = Snooze::new(None);
+    snooze.snooze_until(NOW + 90_000);
+
+    assert!(snooze.is_active(NOW));
+    assert_eq!(snooze.remaining_minutes(NOW), Some(2));
+    assert!(!snooze.expire(NOW + 60_000));
+    assert!(snooze.expire(NOW + 90_000));
+    assert!(!snooze.expire(NOW + 120_000));
+    assert_eq!(snooze.state(), SnoozeState { until_ms: None });
+    assert_eq!(snooze.remaining_minutes(NOW), None);
+}
+
+#[test]
+fn snooze_survives_reload_until_resumed() {
+    let path = temp_file("snooze-reload");
+    Snooze::load(Some(path.clone())).snooze_until(NOW + 60_000);
+
+    let mut reloaded = Snooze::load(Some(path.clone()));
+    assert!(reloaded.is_active(NOW));
+
+    reloaded.resume();
+    assert!(!Snooze::load(Some(path)).is_active(NOW));
+}
+
+#[test]
+fn menu_ids_parse_to_actions() {
+    assert_eq!(
+        parse_menu_id("snooze:15"),
+        Some(SnoozeMenuAction::Snooze(SnoozeDuration::Minutes { minutes: 15 }))
+    );
+    assert_eq!(parse_menu_id("snooze:tomorrow"), Some(SnoozeMenuAction::Snooze(SnoozeDuration::UntilTomorrow)));
+    assert_eq!(parse_menu_id("snooze:resume"), Some(SnoozeMenuAction::Resume));
+    assert_eq!(parse_menu_id("snooze:soon"), None);
+    assert_eq!(parse_menu_id("monitoring"), None);
+}
+
+#[test]
+fn duration_deserializes_from_the_widget() {
+    let minutes: SnoozeDuration = serde_json::from_str(r#"{"kind":"minutes","minutes":60}"#).unwrap();
+    let tomorrow: SnoozeDuration = serde_json::from_str(r#"{"kind":"untilTomorrow"}"#).unwrap();
+
+    assert_eq!(minutes, SnoozeDuration::Minutes { minutes: 60 });
+    assert_eq!(tomorrow, SnoozeDuration::UntilTomorrow);
+}
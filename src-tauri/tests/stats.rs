@@ -0,0 +1,112 @@
+use pavlok_touch_grass_lib::fatigue::MinuteSample;
+use pavlok_touch_grass_lib::history::HistoryRecord;
+use pavlok_touch_grass_lib::pavlok::{Intensity, PavlokError, Stimulus};
+use pavlok_touch_grass_lib::stats::{compute, StatsRange, StimulusCounts};
+
+const WORK_MINUTES: u32 = 3;
+
+/// Minute records one minute apart from `start_ms`, driven by a pattern of
+/// `#` (active) and `.` (a full break, back to zero fatigue).
+fn minutes(start_ms: u64, pattern: &str, fatigue: &mut u32) -> Vec<HistoryRecord> {
+    pattern
+        .chars()
+        .enumerate()
+        .map(|(index, c)| {
+            let active = c == '#';
+            *fatigue = if active { *fatigue + 1 } else { 0 };
+            HistoryRecord::Minute {
+                at_ms: start_ms + index as u64 * 60_000,
+                sample: MinuteSample {
+                    active_seconds: if active { 60 } else { 0 },
+                    active,
+                    fatigue: *fatigue,
+                    rest_streak: 0,
+                    monitoring: true,
+                    work_minutes: WORK_MINUTES,
+                    limit_reached: false,
+                    rested: false,
+                },
+            }
+        })
+        .collect()
+}
+
+fn stimulus(at_ms: u64, stimulus: Stimulus, error: Option<PavlokError>) -> HistoryRecord {
+    HistoryRecord::Stimulus {
+        at_ms,
+        stimulus,
+        intensity: Intensity::new(50).unwrap(),
+        reason: "Fatigue limit".into(),
+        error,
+        retry: false,
+    }
+}
+
+const ALL: StatsRange = StatsRange { from_ms: 0, to_ms: u64::MAX };
+
+#[test]
+fn empty_history_has_no_compliance() {
+    let stats = compute(ALL, &[]);
+
+    assert_eq!(stats.active_minutes, 0);
+    assert_eq!(stats.limit_hits, 0);
+    assert_eq!(stats.break_compliance, None);
+}
+
+#[test]
+fn counts_active_time_and_longest_stretch() {
+    let mut fatigue = 0;
+    let records = minutes(0, "##.####.#", &mut fatigue);
+
+    let stats = compute(ALL, &records);
+
+    assert_eq!(stats.active_minutes, 7);
+    assert_eq!(stats.longest_stretch_minutes, 4);
+}
+
+#[test]
+fn downtime_between_records_breaks_a_stretch() {
+    let mut fatigue = 0;
+    let mut records = minutes(0, "##", &mut fatigue);
+    fatigue = 0;
+    records.extend(minutes(60 * 60_000, "##", &mut fatigue));
+
+    assert_eq!(compute(ALL, &records).longest_stretch_minutes, 2);
+}
+
+#[test]
+fn limit_hits_and_break_compliance() {
+    let mut fatigue = 0;
+    // Hit, keep working, then a full break; hit again and never rest.
+    let records = minutes(0, "#####.####", &mut fatigue);
+
+    let stats = compute(ALL, &records);
+
+    assert_eq!(stats.limit_hits, 2);
+    assert_eq!(stats.break_compliance, Some(50.0));
+}
+
+#[test]
+fn only_accepted_stimuli_are_counted_by_type() {
+    let records = [
+        stimulus(1, Stimulus::Beep, None),
+        stimulus(2, Stimulus::Zap, None),
+        stimulus(3, Stimulus::Zap, None),
+        stimulus(4, Stimulus::Vibe, Some(PavlokError::Network { message: "offline".into() })),
+    ];
+
+    let stats = compute(ALL, &records);
+
+    assert_eq!(stats.stimuli, StimulusCounts { beep: 1, vibe: 0, zap: 2 });
+    assert_eq!(stats.failed_stimuli, 1);
+}
+
+#[test]
+fn records_outside_the_range_are_ignored() {
+    let mut fatigue = 0;
+    let records = minutes(0, "######", &mut fatigue);
+
+    let stats = compute(StatsRange { from_ms: 2 * 60_000, to_ms: 4 * 60_000 }, &records);
+
+    assert_eq!(stats.active_minutes, 2);
+}
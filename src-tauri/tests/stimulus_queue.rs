@@ -0,0 +1,118 @@
+mod support;
+
+use pavlok_touch_grass_lib::alert_queue::{RetryPolicy, StimulusQueue};
+use pavlok_touch_grass_lib::pavlok::{Intensity, PavlokError, Stimulus};
+use std::path::PathBuf;
+use std::time::Duration;
+use support::temp_dir;
+
+const REASON: &str = "Fatigue limit";
+const START_MS: u64 = 1_700_000_000_000;
+
+fn network_error() -> PavlokError {
+    PavlokError::Network { message: "connection refused".into() }
+}
+
+fn level() -> Intensity {
+    Intensity::new(40).unwrap()
+}
+
+fn policy() -> RetryPolicy {
+    RetryPolicy {
+        base_delay: Duration::from_secs(5),
+        max_delay: Duration::from_secs(60),
+        max_age: Duration::from_secs(300),
+        jitter: 0.0,
+    }
+}
+
+fn temp_queue_file(name: &str) -> PathBuf {
+    temp_dir(name).join("queue.json")
+}
+
+#[test]
+fn backoff_doubles_up_to_the_cap() {
+    let policy = policy();
+    let delays: Vec<u64> = (1..=6).map(|attempt| policy.backoff(attempt, 0.5).as_secs()).collect();
+    assert_eq!(delays, [5, 10, 20, 40, 60, 60]);
+}
+
+#[test]
+fn backoff_jitter_stays_within_bounds() {
+    let policy = RetryPolicy { jitter: 0.2, ..policy() };
+    assert_eq!(policy.backoff(2, 0.0), Duration::from_secs(8));
+    assert_eq!(policy.backoff(2, 0.5), Duration::from_secs(10));
+    assert!(policy.backoff(2, 0.999) <= Duration::from_secs(12));
+}
+
+#[test]
+fn only_transient_failures_are_queued() {
+    let mut queue = StimulusQueue::new(None, policy());
+    assert!(!queue.push_failed(Stimulus::Zap, level(), REASON, &PavlokError::Unauthorized, START_MS));
+    assert!(!queue.push_failed(Stimulus::Zap, level(), REASON, &PavlokError::Server { status: 400 }, START_MS));
+    assert!(queue.push_failed(Stimulus::Zap, level(), REASON, &PavlokError::Server { status: 503 }, START_MS));
+    assert_eq!(queue.items().len(), 1);
+}
+
+#[test]
+fn entries_come_due_after_backoff() {
+    let mut queue = StimulusQueue::new(None, policy());
+    queue.push_failed(Stimulus::Beep, level(), REASON, &network_error(), START_MS);
+
+    assert!(queue.take_due(START_MS + 4_999).is_none());
+    let item = queue.take_due(START_MS + 5_000).unwrap();
+    assert_eq!(item.stimulus, Stimulus::Beep);
+    assert!(queue.items().is_empty());
+
+    queue.retry_failed(item, &network_error(), START_MS + 5_000);
+    assert_eq!(queue.items()[0].attempts, 2);
+    assert_eq!(queue.items()[0].next_attempt_at_ms, START_MS + 15_000);
+}
+
+#[test]
+fn retry_after_overrides_shorter_backoff() {
+    let mut queue = StimulusQueue::new(None, policy());
+    queue.push_failed(Stimulus::Beep, level(), REASON, &PavlokError::RateLimited { retry_after: Some(42) }, START_MS);
+    assert_eq!(queue.items()[0].next_attempt_at_ms, START_MS + 42_000);
+}
+
+#[test]
+fn stale_entries_are_dropped() {
+    let mut queue = StimulusQueue::new(None, policy());
+    queue.push_failed(Stimulus::Vibe, level(), REASON, &network_error(), START_MS);
+    assert!(queue.take_due(START_MS + 301_000).is_none());
+    assert!(queue.items().is_empty());
+}
+
+#[test]
+fn repeated_failures_coalesce_and_delivery_clears() {
+    let mut queue = StimulusQueue::new(None, policy());
+    queue.push_failed(Stimulus::Zap, level(), REASON, &network_error(), START_MS);
+    queue.push_failed(Stimulus::Zap, level(), REASON, &network_error(), START_MS + 60_000);
+    queue.push_failed(Stimulus::Beep, level(), REASON, &network_error(), START_MS + 60_000);
+    assert_eq!(queue.items().len(), 2);
+
+    queue.delivered(Stimulus::Zap, REASON);
+    assert_eq!(queue.items().len(), 1);
+    assert_eq!(queue.items()[0].stimulus, Stimulus::Beep);
+}
+
+#[test]
+fn queue_survives_reload_without_token() {
+    let path = temp_queue_file("reload");
+    {
+        let mut queue = StimulusQueue::new(Some(path.clone()), policy());
+        queue.set_token("secret-token");
+        queue.push_failed(Stimulus::Zap, level(), REASON, &network_error(), START_MS);
+    }
+
+    let raw = std::fs::read_to_string(&path).unwrap();
+    assert!(!raw.contains("secret-token"));
+
+    let reloaded = StimulusQueue::load(Some(path.clone()), policy());
+    assert_eq!(reloaded.items().len(), 1);
+    assert_eq!(reloaded.items()[0].stimulus, Stimulus::Zap);
+    assert_eq!(reloaded.items()[0].intensity, level());
+    assert!(reloaded.token().is_none());
+    let _ = std::fs::remove_file(path);
+}
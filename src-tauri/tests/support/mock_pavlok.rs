@@ -0,0 +1,177 @@
+//! Minimal stand-in for the Pavlok v5 API. It records every request and
+//! replies from a scripted queue, falling back to `200 OK` once it is empty.
+//! Successful `GET /user/me` calls answer with the configured account.
+#![allow(dead_code)]
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use tiny_http::{Header, Response, Server};
+
+#[derive(Debug, Clone)]
+pub enum MockResponse {
+    Ok,
+    Status(u16),
+    RateLimited { retry_after: Option<u64> },
+    /// Holds the connection open before answering, to trigger client timeouts.
+    Hang(Duration),
+}
+
+#[derive(Debug, Clone)]
+pub struct ReceivedRequest {
+    pub method: String,
+    pub path: String,
+    pub authorization: Option<String>,
+    pub body: serde_json::Value,
+}
+
+impl ReceivedRequest {
+    pub fn stimulus_type(&self) -> Option<&str> {
+        self.body["stimulus"]["stimulusType"].as_str()
+    }
+
+    pub fn stimulus_value(&self) -> Option<u64> {
+        self.body["stimulus"]["stimulusValue"].as_u64()
+    }
+}
+
+struct MockState {
+    script: VecDeque<MockResponse>,
+    received: Vec<ReceivedRequest>,
+    account: serde_json::Value,
+}
+
+impl Default for MockState {
+    fn default() -> Self {
+        Self {
+            script: VecDeque::new(),
+            received: Vec::new(),
+            account: serde_json::json!({
+                "name": "Mock User",
+                "email": "mock@example.com",
+                "devices": [{ "id": "mock-device", "name": "Pavlok 3", "model": "pavlok3", "battery": 87 }]
+            }),
+        }
+    }
+}
+
+pub struct MockPavlok {
+    server: Arc<Server>,
+    state: Arc<Mutex<MockState>>,
+    worker: Option<JoinHandle<()>>,
+    base_url: String,
+}
+
+impl MockPavlok {
+    /// Starts on an ephemeral localhost port.
+    pub fn start() -> Self {
+        Self::bind("127.0.0.1:0")
+    }
+
+    pub fn bind(addr: &str) -> Self {
+        let server = Arc::new(Server::http(addr).expect("failed to bind mock Pavlok server"));
+        let local_addr = server.server_addr().to_ip().expect("mock server is not on TCP");
+        let base_url = format!("http://{local_addr}/api/v5");
+        let state = Arc::new(Mutex::new(MockState::default()));
+
+        let worker = {
+            let server = server.clone();
+            let state = state.clone();
+            thread::spawn(move || {
+                for request in server.incoming_requests() {
+                    let state = state.clone();
+                    // One thread per request so a hanging reply doesn't stall the next one.
+                    thread::spawn(move || handle(request, &state));
+                }
+            })
+        };
+
+        Self { server, state, worker: Some(worker), base_url }
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Queues replies for the next requests, in order.
+    pub fn respond_with(&self, responses: impl IntoIterator<Item = MockResponse>) {
+        self.state.lock().unwrap().script.extend(responses);
+    }
+
+    /// Body returned by `GET /user/me`.
+    pub fn set_account(&self, account: serde_json::Value) {
+        self.state.lock().unwrap().account = account;
+    }
+
+    pub fn requests(&self) -> Vec<ReceivedRequest> {
+        self.state.lock().unwrap().received.clone()
+    }
+
+    /// Requests that hit the stimulus endpoint.
+    pub fn stimuli(&self) -> Vec<ReceivedRequest> {
+        self.requests()
+            .into_iter()
+            .filter(|request| request.path.ends_with("/stimulus/send"))
+            .collect()
+    }
+}
+
+impl Drop for MockPavlok {
+    fn drop(&mut self) {
+        self.server.unblock();
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+fn handle(mut request: tiny_http::Request, state: &Mutex<MockState>) {
+    let mut raw_body = String::new();
+    let _ = request.as_reader().read_to_string(&mut raw_body);
+    let authorization = request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv("Authorization"))
+        .map(|header| header.value.as_str().to_string());
+
+    let is_account = request.url().ends_with("/user/me");
+    let (reply, account) = {
+        let mut state = state.lock().unwrap();
+        state.received.push(ReceivedRequest {
+            method: request.method().as_str().to_string(),
+            path: request.url().to_string(),
+            authorization,
+            body: serde_json::from_str(&raw_body).unwrap_or(serde_json::Value::Null),
+        });
+        (state.script.pop_front().unwrap_or(MockResponse::Ok), state.account.to_string())
+    };
+
+    let response = match reply {
+        MockResponse::Ok if is_account => json_response(200, &account),
+        MockResponse::Ok => json_response(200, r#"{"success":true}"#),
+        MockResponse::Status(status) => json_response(status, r#"{"success":false}"#),
+        MockResponse::RateLimited { retry_after } => {
+            let response = json_response(429, r#"{"success":false}"#);
+            match retry_after {
+                Some(seconds) => response.with_header(header("Retry-After", &seconds.to_string())),
+                None => response,
+            }
+        }
+        MockResponse::Hang(delay) => {
+            thread::sleep(delay);
+            json_response(200, r#"{"success":true}"#)
+        }
+    };
+    let _ = request.respond(response);
+}
+
+fn json_response(status: u16, body: &str) -> Response<std::io::Cursor<Vec<u8>>> {
+    Response::from_string(body)
+        .with_status_code(status)
+        .with_header(header("Content-Type", "application/json"))
+}
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name.as_bytes(), value.as_bytes()).expect("invalid header")
+}
@@ -0,0 +1,28 @@
+//! Fixtures shared by the integration tests.
+#![allow(dead_code)]
+
+pub mod mock_pavlok;
+
+use pavlok_touch_grass_lib::fatigue::{FatigueSnapshot, DEFAULT_BREAK_MINUTES};
+use std::path::PathBuf;
+
+/// An empty directory private to this test process.
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("touch-grass-{}-{name}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// A monitoring engine at `fatigue` of `work_minutes`, with the default break.
+pub fn snapshot(fatigue: u32, work_minutes: u32) -> FatigueSnapshot {
+    FatigueSnapshot {
+        fatigue,
+        percent: fatigue as f64 / work_minutes as f64 * 100.0,
+        rest_streak: 0,
+        monitoring: true,
+        at_limit: fatigue >= work_minutes,
+        work_minutes,
+        break_minutes: DEFAULT_BREAK_MINUTES,
+    }
+}
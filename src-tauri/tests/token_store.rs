@@ -0,0 +1,159 @@
+mod support;
+
+use pavlok_touch_grass_lib::token_store::{EncryptedFileStore, SecretStore, TokenStorage, TokenStore};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use support::temp_dir;
+
+const TOKEN: &str = "secret-token";
+
+/// Not created yet, so the first save has to create it.
+fn store_dir(name: &str) -> PathBuf {
+    temp_dir(name).join("store")
+}
+
+/// In-memory keyring that can be switched off to force the file fallback.
+#[derive(Clone, Default)]
+struct FakeKeyring {
+    value: Arc<Mutex<Option<String>>>,
+    unavailable: bool,
+}
+
+impl SecretStore for FakeKeyring {
+    fn save(&self, token: &str) -> Result<(), String> {
+        if self.unavailable {
+            return Err("no secret service".into());
+        }
+        *self.value.lock().unwrap() = Some(token.to_string());
+        Ok(())
+    }
+
+    fn load(&self) -> Result<Option<String>, String> {
+        if self.unavailable {
+            return Err("no secret service".into());
+        }
+        Ok(self.value.lock().unwrap().clone())
+    }
+
+    fn clear(&self) -> Result<(), String> {
+        *self.value.lock().unwrap() = None;
+        Ok(())
+    }
+}
+
+#[test]
+fn encrypted_file_round_trips_without_plain_text() {
+    let dir = store_dir("token-roundtrip");
+    let store = EncryptedFileStore::new(&dir);
+
+    store.save(TOKEN).unwrap();
+
+    let raw = std::fs::read(dir.join("api-token.enc")).unwrap();
+    assert!(!String::from_utf8_lossy(&raw).contains(TOKEN));
+    assert_eq!(EncryptedFileStore::new(&dir).load().unwrap().as_deref(), Some(TOKEN));
+
+    store.clear().unwrap();
+    assert_eq!(store.load().unwrap(), None);
+}
+
+#[test]
+fn encrypted_file_rejects_tampering() {
+    let dir = store_dir("token-tamper");
+    let store = EncryptedFileStore::new(&dir);
+    store.save(TOKEN).unwrap();
+
+    let path = dir.join("api-token.enc");
+    let mut raw = std::fs::read(&path).unwrap();
+    *raw.last_mut().unwrap() ^= 0xff;
+    std::fs::write(&path, raw).unwrap();
+
+    assert!(store.load().is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn encrypted_file_is_owner_only() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = store_dir("token-perms");
+    EncryptedFileStore::new(&dir).save(TOKEN).unwrap();
+
+    for name in ["api-token.enc", "api-token.key"] {
+        let mode = std::fs::metadata(dir.join(name)).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600, "{name}");
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn rewritten_file_is_made_owner_only() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = store_dir("token-perms-existing");
+    let store = EncryptedFileStore::new(&dir);
+    store.save(TOKEN).unwrap();
+    std::fs::set_permissions(dir.join("api-token.enc"), std::fs::Permissions::from_mode(0o644)).unwrap();
+
+    store.save(TOKEN).unwrap();
+
+    let mode = std::fs::metadata(dir.join("api-token.enc")).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+}
+
+#[test]
+fn keyring_is_preferred_and_replaces_file_copy() {
+    let dir = store_dir("token-keyring");
+    EncryptedFileStore::new(&dir).save("old-token").unwrap();
+    let keyring = FakeKeyring::default();
+    let store = TokenStore::new(Box::new(keyring.clone()), Some(Box::new(EncryptedFileStore::new(&dir))));
+
+    assert_eq!(store.save(&format!("  {TOKEN} ")), Ok(TokenStorage::Keyring));
+
+    assert_eq!(keyring.value.lock().unwrap().as_deref(), Some(TOKEN));
+    assert!(!dir.join("api-token.enc").exists());
+    assert_eq!(store.token().as_deref(), Some(TOKEN));
+}
+
+#[test]
+fn falls_back_to_encrypted_file_without_keyring() {
+    let dir = store_dir("token-fallback");
+    let keyring = FakeKeyring { unavailable: true, ..FakeKeyring::default() };
+    let store = TokenStore::new(Box::new(keyring.clone()), Some(Box::new(EncryptedFileStore::new(&dir))));
+
+    assert_eq!(store.save(TOKEN), Ok(TokenStorage::EncryptedFile));
+
+    let reloaded = TokenStore::new(Box::new(keyring), Some(Box::new(EncryptedFileStore::new(&dir))));
+    assert!(reloaded.load());
+    assert_eq!(reloaded.token().as_deref(), Some(TOKEN));
+}
+
+#[test]
+fn unreadable_store_is_an_error_not_an_empty_one() {
+    let dir = store_dir("token-unreadable");
+    let keyring = FakeKeyring { unavailable: true, ..FakeKeyring::default() };
+
+    assert!(TokenStore::new(Box::new(keyring.clone()), None).try_load().is_err());
+    let with_fallback = TokenStore::new(Box::new(keyring), Some(Box::new(EncryptedFileStore::new(&dir))));
+    assert_eq!(with_fallback.try_load(), Ok(false));
+}
+
+#[test]
+fn clear_forgets_token_everywhere() {
+    let dir = store_dir("token-clear");
+    let keyring = FakeKeyring::default();
+    let store = TokenStore::new(Box::new(keyring.clone()), Some(Box::new(EncryptedFileStore::new(&dir))));
+    store.save(TOKEN).unwrap();
+
+    store.clear().unwrap();
+
+    assert_eq!(store.token(), None);
+    assert!(!store.load());
+    assert_eq!(*keyring.value.lock().unwrap(), None);
+}
+
+#[test]
+fn empty_token_is_not_saved() {
+    let store = TokenStore::new(Box::new(FakeKeyring::default()), None);
+    assert!(store.save("   ").is_err());
+    assert_eq!(store.token(), None);
+}
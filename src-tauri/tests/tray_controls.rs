@@ -0,0 +1,23 @@
+use pavlok_touch_grass_lib::pavlok::Stimulus;
+use pavlok_touch_grass_lib::settings::{Settings, SettingsPatch};
+use pavlok_touch_grass_lib::tray_controls::{TrayAction, PRESETS};
+
+#[test]
+fn menu_ids_parse_to_actions() {
+    assert_eq!(TrayAction::parse_id("monitoring"), Some(TrayAction::ToggleMonitoring));
+    assert_eq!(TrayAction::parse_id("alert_mode:zap"), Some(TrayAction::AlertMode(Stimulus::Zap)));
+    assert_eq!(
+        TrayAction::parse_id("preset:50:10"),
+        Some(TrayAction::Preset { work_minutes: 50, break_minutes: 10 })
+    );
+    assert_eq!(TrayAction::parse_id("preset:50"), None);
+    assert_eq!(TrayAction::parse_id("reset_fatigue"), None);
+}
+
+#[test]
+fn presets_are_valid_settings() {
+    for (work, brk) in PRESETS {
+        let patch = SettingsPatch { work_minutes: Some(work), break_minutes: Some(brk), ..SettingsPatch::default() };
+        assert!(patch.apply_to(&Settings::default()).validate().is_ok(), "{work}/{brk}");
+    }
+}
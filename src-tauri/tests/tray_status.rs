@@ -0,0 +1,57 @@
+mod support;
+
+use pavlok_touch_grass_lib::fatigue::FatigueSnapshot;
+use pavlok_touch_grass_lib::tray_status::{filled_rows, render, tooltip, IconState, ICON_SIZE};
+
+fn snapshot(fatigue: u32, monitoring: bool) -> FatigueSnapshot {
+    FatigueSnapshot { monitoring, ..support::snapshot(fatigue, 45) }
+}
+
+fn pixel(rgba: &[u8], x: u32, y: u32) -> [u8; 4] {
+    let offset = ((y * ICON_SIZE + x) * 4) as usize;
+    rgba[offset..offset + 4].try_into().unwrap()
+}
+
+fn has_color(rgba: &[u8], color: [u8; 4]) -> bool {
+    rgba.chunks(4).any(|px| px[..3] == color[..3] && px[3] > 0)
+}
+
+#[test]
+fn tooltip_shows_minutes_to_limit() {
+    assert_eq!(tooltip(&snapshot(28, true), None), "Fatigue 62% — 17 min to limit");
+    assert_eq!(tooltip(&snapshot(45, true), None), "Fatigue 100% — limit reached");
+    assert_eq!(tooltip(&snapshot(28, false), None), "Fatigue 62% — paused");
+    assert_eq!(tooltip(&snapshot(28, true), Some(12)), "Fatigue 62% — 17 min to limit\nAlerts snoozed, 12 min left");
+}
+
+#[test]
+fn icon_state_follows_the_engine() {
+    assert_eq!(IconState::of(&snapshot(10, true)), IconState::Active);
+    assert_eq!(IconState::of(&snapshot(45, true)), IconState::AtLimit);
+    assert_eq!(IconState::of(&snapshot(45, false)), IconState::Paused);
+}
+
+#[test]
+fn fill_rises_from_the_bottom() {
+    let rows = filled_rows(50.0, ICON_SIZE);
+    let rgba = render(rows, IconState::Active, ICON_SIZE);
+
+    assert_eq!(rows, ICON_SIZE / 2);
+    assert_eq!(rgba.len(), (ICON_SIZE * ICON_SIZE * 4) as usize);
+    // Inside the bolt's lower stem (x=10..17 in the 24px viewBox) and its top block.
+    assert_eq!(pixel(&rgba, 14, 22), IconState::Active.fill_color());
+    assert_ne!(pixel(&rgba, 12, 4)[..3], IconState::Active.fill_color()[..3]);
+    // Outside the bolt stays transparent.
+    assert_eq!(pixel(&rgba, 0, 0)[3], 0);
+}
+
+#[test]
+fn colours_match_the_state() {
+    let full = filled_rows(150.0, ICON_SIZE);
+    assert_eq!(full, ICON_SIZE);
+
+    assert!(has_color(&render(full, IconState::AtLimit, ICON_SIZE), IconState::AtLimit.fill_color()));
+    let paused = render(full, IconState::Paused, ICON_SIZE);
+    assert!(!has_color(&paused, IconState::Active.fill_color()));
+    assert!(!has_color(&render(0, IconState::Active, ICON_SIZE), IconState::Active.fill_color()));
+}
@@ -0,0 +1,107 @@
+mod support;
+
+use pavlok_touch_grass_lib::pavlok::{Intensity, PavlokError, Stimulus};
+use pavlok_touch_grass_lib::webhooks::{
+    deliver, render_body, sign, Webhook, WebhookEvent, WebhookPayload, EVENT_HEADER, SIGNATURE_HEADER,
+};
+use std::thread;
+use std::time::Duration;
+use tiny_http::Server;
+
+fn payload(event: WebhookEvent) -> WebhookPayload {
+    WebhookPayload { at_ms: 1_700_000_000_000, ..WebhookPayload::new(event, support::snapshot(50, 50)) }
+}
+
+fn webhook(template: Option<&str>) -> Webhook {
+    Webhook { url: "https://example.com/hook".into(), template: template.map(String::from), ..Webhook::default() }
+}
+
+#[test]
+fn default_body_is_the_json_payload() {
+    let failed = WebhookPayload {
+        stimulus: Some(Stimulus::Zap),
+        intensity: Some(Intensity::new(40).unwrap()),
+        error: Some(PavlokError::Unauthorized),
+        ..payload(WebhookEvent::StimulusFailed)
+    };
+
+    let body: serde_json::Value = serde_json::from_str(&render_body(&webhook(None), &failed)).unwrap();
+
+    assert_eq!(body["event"], "stimulusFailed");
+    assert_eq!(body["atMs"], 1_700_000_000_000u64);
+    assert_eq!(body["state"]["percent"], 100.0);
+    assert_eq!(body["stimulus"], "zap");
+    assert_eq!(body["intensity"], 40);
+    assert_eq!(body["error"]["kind"], "unauthorized");
+
+    let plain: serde_json::Value = serde_json::from_str(&render_body(&webhook(None), &payload(WebhookEvent::Rested))).unwrap();
+    assert!(plain.get("stimulus").is_none());
+}
+
+#[test]
+fn templates_fill_in_placeholders() {
+    let slack = webhook(Some(r#"{"text": "{{ event }}: fatigue {{percent}}% after {{fatigue}} min {{unknown}}"}"#));
+
+    assert_eq!(
+        render_body(&slack, &payload(WebhookEvent::LimitReached)),
+        r#"{"text": "limitReached: fatigue 100% after 50 min {{unknown}}"}"#
+    );
+}
+
+#[test]
+fn template_values_are_json_escaped() {
+    let failed = WebhookPayload {
+        error: Some(PavlokError::Network { message: "bad \"gateway\"".into() }),
+        ..payload(WebhookEvent::StimulusFailed)
+    };
+
+    let body = render_body(&webhook(Some(r#"{"error": "{{error}}"}"#)), &failed);
+
+    let parsed: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(parsed["error"], "network error: bad \"gateway\"");
+}
+
+#[test]
+fn signature_is_hmac_sha256() {
+    // RFC 4231, test case 2.
+    assert_eq!(
+        sign("Jefe", "what do ya want for nothing?"),
+        "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+    );
+}
+
+#[test]
+fn events_filter_and_urls_are_checked() {
+    let limits_only = Webhook { events: vec![WebhookEvent::LimitReached], ..webhook(None) };
+    assert!(limits_only.wants(WebhookEvent::LimitReached));
+    assert!(!limits_only.wants(WebhookEvent::Rested));
+    assert!(webhook(None).wants(WebhookEvent::MonitoringStopped));
+
+    assert!(webhook(None).validate().is_ok());
+    assert!(Webhook { url: "ftp://example.com".into(), ..Webhook::default() }.validate().is_err());
+}
+
+#[test]
+fn delivery_posts_signed_body() {
+    let server = Server::http("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/hook", server.server_addr().to_ip().unwrap());
+    let hook = Webhook { url, secret: Some("s3cret".into()), ..Webhook::default() };
+
+    let sender = thread::spawn(move || {
+        let client = reqwest::blocking::Client::builder().timeout(Duration::from_secs(2)).build().unwrap();
+        deliver(&client, &hook, WebhookEvent::Rested, r#"{"event":"rested"}"#.into())
+    });
+    let mut request = server.recv_timeout(Duration::from_secs(2)).unwrap().unwrap();
+    let header = |name: &str| {
+        request.headers().iter().find(|header| header.field.to_string().eq_ignore_ascii_case(name)).map(|header| header.value.to_string())
+    };
+    let (event, signature) = (header(EVENT_HEADER), header(SIGNATURE_HEADER));
+    let mut body = String::new();
+    request.as_reader().read_to_string(&mut body).unwrap();
+    request.respond(tiny_http::Response::empty(204)).unwrap();
+
+    assert_eq!(sender.join().unwrap(), Ok(()));
+    assert_eq!(body, r#"{"event":"rested"}"#);
+    assert_eq!(event.as_deref(), Some("rested"));
+    assert_eq!(signature, Some(sign("s3cret", &body)));
+}
@@ -23,6 +23,16 @@ document.addEventListener('DOMContentLoaded', () => {
   const fatigueValueNumber = document.getElementById('fatigue-value-number');
   const boltFillRect = document.getElementById('bolt-fill-rect');
 
+  const snoozeDisplay = document.getElementById('snooze-display');
+
+  const statsView = document.getElementById('stats-view');
+  const statsBody = document.getElementById('stats-body');
+  const statsTabs = document.querySelectorAll('.stats-tab');
+  const statsCloseBtn = document.getElementById('stats-close');
+  const statsCurrentLabel = document.getElementById('stats-current-label');
+  const statsPreviousLabel = document.getElementById('stats-previous-label');
+  const statsApiTokenBtn = document.getElementById('stats-api-token');
+
   // Icons
   const icons = {
     beep: document.getElementById('icon-beep'),
@@ -35,14 +45,20 @@ document.addEventListener('DOMContentLoaded', () => {
   let currentModeIndex = 0; // Start with beep
   let isMonitoring = false;
 
-  // Logic State
-  let fatigue = 0;
-  let restStreak = 0;
-  let activeSeconds = 0;
-  let secondCounter = 0;
-  let lastAlertTime = 0;
+  // Engine state as last reported by the backend
+  let fatigueState = null;
+  // Per-stimulus intensity levels (set from the tray)
+  let intensities = null;
   let apiKeyInvalid = false;
-  let lastTickAt = null;
+  // Set when the account lookup rejects the key, so it is flagged before the limit
+  let apiKeyRejected = false;
+  // The token itself lives in the backend (OS keyring); the widget only knows whether one is saved
+  let hasApiKey = false;
+  const apiPlaceholder = apiInput.placeholder;
+  // 'day' or 'week'; the stats view compares it with the period before
+  let statsRange = 'day';
+  // Set while the backend holds stimuli off; fatigue keeps counting
+  let snoozeUntilMs = null;
 
   function setProgress(ringPercent, displayPercent = ringPercent) {
     const normalized = Math.max(0, Math.min(100, ringPercent));
@@ -69,100 +85,115 @@ document.addEventListener('DOMContentLoaded', () => {
   }
 
   function refreshFatigueUI() {
-    const workLimit = getWorkLimit();
-    const fatiguePercent = (fatigue / workLimit) * 100;
-    setProgress(fatiguePercent, fatiguePercent);
+    const percent = fatigueState ? fatigueState.percent : 0;
+    setProgress(percent, percent);
     updateApiWarningState();
   }
 
-  function applyMinute() {
-    const workLimit = getWorkLimit();
-    const breakLimit = getBreakLimit();
-    const minuteActiveSeconds = activeSeconds;
-    const previousFatigue = fatigue;
-
-    if (minuteActiveSeconds >= 10) {
-      // While paused, activity should not increase fatigue.
-      if (isMonitoring) {
-        fatigue++;
-      }
-      restStreak = 0;
-    } else {
-      if (fatigue > 0) fatigue--;
-      restStreak++;
-    }
-
-    activeSeconds = 0;
-    secondCounter = 0;
-
-    if (restStreak >= breakLimit) {
-      fatigue = 0;
-    }
-
-    if (previousFatigue > 0 && fatigue === 0) {
-      sendDoubleVibroAlertOnce();
+  function applyFatigueUpdate(update) {
+    if (!update) return;
+    fatigueState = update.state;
+    // Monitoring can be restored from the previous run, so follow the backend.
+    if (fatigueState && fatigueState.monitoring !== isMonitoring) {
+      isMonitoring = fatigueState.monitoring;
+      appCircle.classList.toggle('monitoring', isMonitoring);
     }
-
-    const isAtLimit = fatigue >= workLimit;
-    if (isAtLimit && isMonitoring) {
-      const now = Date.now();
-      if (now - lastAlertTime >= 60000) {
-        sendAlert();
-        lastAlertTime = now;
-      }
-    }
-
     refreshFatigueUI();
   }
 
-  function applySampleSeconds(activeSecs, inactiveSecs) {
-    const totalSeconds = Math.max(0, activeSecs + inactiveSecs);
-    if (totalSeconds === 0) return;
-    if (activeSecs > 0) activeSeconds += activeSecs;
-
-    let remaining = totalSeconds;
-    while (remaining > 0) {
-      const toMinuteBoundary = 60 - secondCounter;
-      const step = Math.min(remaining, toMinuteBoundary);
-      secondCounter += step;
-      remaining -= step;
-
-      if (secondCounter >= 60) {
-        applyMinute();
-      }
+  async function syncLimits() {
+    try {
+      fatigueState = await invoke('set_fatigue_limits', {
+        workMinutes: getWorkLimit(),
+        breakMinutes: getBreakLimit()
+      }) || fatigueState;
+    } catch (e) {
+      console.error("Invoke Error:", e);
     }
-
+    refreshFatigueUI();
   }
 
   function isAtLimit() {
-    return fatigue >= getWorkLimit();
+    return Boolean(fatigueState && fatigueState.atLimit);
   }
 
   function updateApiWarningState() {
-    appCircle.classList.toggle('api-key-invalid', isAtLimit() && apiKeyInvalid);
+    appCircle.classList.toggle('api-key-invalid', apiKeyRejected || (isAtLimit() && apiKeyInvalid));
     updateBoltTooltip();
   }
 
   // --- Persistence & Initialization ---
-  if (localStorage.getItem('workTime')) workInput.value = localStorage.getItem('workTime');
-  if (localStorage.getItem('breakTime')) breakInput.value = localStorage.getItem('breakTime');
-  if (localStorage.getItem('apiToken')) apiInput.value = localStorage.getItem('apiToken');
-  {
-    const savedMode = localStorage.getItem('alertMode');
-    const savedModeIndex = modes.indexOf(savedMode);
-    if (savedModeIndex >= 0) currentModeIndex = savedModeIndex;
+  // Settings live in the backend's settings.toml; the widget only mirrors them.
+  function applySettings(settings) {
+    if (!settings) return;
+    if (document.activeElement !== workInput) workInput.value = settings.workMinutes;
+    if (document.activeElement !== breakInput) breakInput.value = settings.breakMinutes;
+    const modeIndex = modes.indexOf(settings.alertMode === 'vibe' ? 'vibro' : settings.alertMode);
+    if (modeIndex >= 0) currentModeIndex = modeIndex;
+    intensities = settings.intensity || intensities;
+    statsApiTokenBtn.hidden = !(settings.localApi && settings.localApi.enabled);
+    updateModeUI();
+  }
+
+  // Hands values saved by older versions (localStorage) to the backend once, then drops them.
+  async function importLegacySettings() {
+    const keys = { workTime: 'workTime', breakTime: 'breakTime', alertMode: 'alertMode', apiToken: 'apiToken' };
+    const legacy = {};
+    for (const [field, key] of Object.entries(keys)) {
+      const value = localStorage.getItem(key);
+      if (value !== null) legacy[field] = value;
+    }
+    if (Object.keys(legacy).length === 0) return;
+    try {
+      await invoke('import_legacy_settings', { legacy });
+      Object.values(keys).forEach(key => localStorage.removeItem(key));
+    } catch (e) {
+      console.error("Migrating settings failed:", e);
+    }
+  }
+
+  async function initSettings() {
+    await importLegacySettings();
+    try {
+      applySettings(await invoke('get_settings'));
+    } catch (e) {
+      console.error("Invoke Error:", e);
+    }
+    syncLimits();
+    initApiKey();
+    try {
+      applySnooze(await invoke('get_snooze'));
+    } catch (e) {
+      console.error("Invoke Error:", e);
+    }
   }
 
-  // Tray action: reset fatigue from native menu
+  // Engine state pushed by the backend sampling loop (also covers tray actions)
   if (tauri?.event?.listen) {
-    tauri.event.listen("reset-fatigue", () => {
-      resetFatigue();
+    tauri.event.listen("fatigue-updated", (event) => {
+      applyFatigueUpdate(event.payload);
+    });
+    tauri.event.listen("alert-result", (event) => {
+      applyAlertResult(event.payload);
+    });
+    tauri.event.listen("api-token-changed", (event) => {
+      setHasApiKey(Boolean(event.payload && event.payload.saved));
+      apiKeyRejected = false;
+      apiInput.title = '';
+      updateApiWarningState();
+    });
+    tauri.event.listen("settings-updated", (event) => {
+      applySettings(event.payload);
+    });
+    tauri.event.listen("snooze-updated", (event) => {
+      applySnooze(event.payload);
     });
   }
 
   updateModeUI();
   updateBoltTooltip();
   setProgress(0, 0);
+  initSettings();
 
   function getResizeDirectionFromPointerEvent(e) {
     const rect = appCircle.getBoundingClientRect();
@@ -194,6 +225,9 @@ document.addEventListener('DOMContentLoaded', () => {
     }
 
     if (e.target.closest('.control-btn') ||
+      e.target.closest('.fatigue-value') ||
+      e.target.closest('.snooze-display') ||
+      e.target.closest('.stats-view') ||
       e.target.closest('.timers-container') ||
       e.target.closest('.api-container') ||
       e.target.closest('.pavlok-bolt') ||
@@ -221,10 +255,10 @@ document.addEventListener('DOMContentLoaded', () => {
   // --- Event Listeners ---
 
   // 1. Bolt Click (Toggle Monitor)
-  boltIcon.addEventListener('click', (e) => {
+  boltIcon.addEventListener('click', async (e) => {
     e.stopPropagation();
     isMonitoring = !isMonitoring;
-    updateMonitoringState();
+    await updateMonitoringState();
     triggerHapticVisual(boltIcon);
     triggerChargeBurst();
   });
@@ -234,19 +268,70 @@ document.addEventListener('DOMContentLoaded', () => {
     e.stopPropagation();
     currentModeIndex = (currentModeIndex + 1) % modes.length;
     updateModeUI();
-    localStorage.setItem('alertMode', modes[currentModeIndex]);
+    const mode = modes[currentModeIndex];
+    invoke('update_settings', { patch: { alertMode: mode === 'vibro' ? 'vibe' : mode } })
+      .catch(e => console.error("Invoke Error:", e));
     triggerHapticVisual(modeBtn);
   });
 
+  // 3. Snooze countdown (click resumes alerts early)
+  snoozeDisplay.addEventListener('click', (e) => {
+    e.stopPropagation();
+    invoke('resume_alerts').then(applySnooze).catch(e => console.error("Invoke Error:", e));
+  });
+  setInterval(renderSnooze, 1000);
+
+  // 4. Stats View
+  fatigueDisplay.addEventListener('click', (e) => {
+    e.stopPropagation();
+    openStats();
+  });
+  statsCloseBtn.addEventListener('click', (e) => {
+    e.stopPropagation();
+    statsView.hidden = true;
+  });
+  statsTabs.forEach(tab => {
+    tab.addEventListener('click', (e) => {
+      e.stopPropagation();
+      statsRange = tab.dataset.range;
+      statsTabs.forEach(t => t.classList.toggle('active', t === tab));
+      refreshStats();
+    });
+  });
+  // The token is only fetched on this click; settings never carry it.
+  statsApiTokenBtn.addEventListener('click', async (e) => {
+    e.stopPropagation();
+    try {
+      await navigator.clipboard.writeText(await invoke('reveal_local_api_token'));
+      statsApiTokenBtn.textContent = 'Copied';
+    } catch (error) {
+      console.error("Copying the local API token failed:", error);
+      statsApiTokenBtn.textContent = 'Copy failed';
+    }
+    setTimeout(() => { statsApiTokenBtn.textContent = 'Copy local API token'; }, 2000);
+  });
+
   // 5. API Token
   if (apiInput) {
-    apiInput.addEventListener('change', () => {
-      localStorage.setItem('apiToken', apiInput.value);
+    apiInput.addEventListener('change', async () => {
+      const token = apiInput.value.trim();
+      if (!token) return;
+      try {
+        await invoke('save_api_token', { token });
+        setHasApiKey(true);
+      } catch (e) {
+        console.error("Saving API key failed:", e);
+        return;
+      }
+      // Don't keep the token around in the page once the backend has it
+      apiInput.value = '';
       apiKeyInvalid = false;
       updateApiWarningState();
+      validateApiKey();
     });
     apiInput.addEventListener('input', () => {
       apiKeyInvalid = false;
+      apiKeyRejected = false;
       updateApiWarningState();
     });
     apiInput.addEventListener('mousedown', (e) => e.stopPropagation());
@@ -269,67 +354,136 @@ document.addEventListener('DOMContentLoaded', () => {
     input.addEventListener('mousedown', (e) => e.stopPropagation());
   });
 
-  // --- Monitoring Loop (1s Tick) ---
-  setInterval(async () => {
-    const now = Date.now();
-    if (lastTickAt == null) {
-      lastTickAt = now;
+  // Backend errors arrive as { kind: 'unauthorized' | 'rateLimited' | 'server' | 'network' | 'invalidToken', ... }
+  // Transient ones (network, rate limit, 5xx) are retried by the backend queue.
+  function applyAlertResult(error) {
+    if (!error) {
+      apiKeyInvalid = false;
+    } else {
+      console.error("Alert Failed:", error);
+      if (error.kind === 'unauthorized' || error.kind === 'invalidToken') {
+        apiKeyInvalid = true;
+      }
     }
-    const elapsedSeconds = Math.max(1, Math.floor((now - lastTickAt) / 1000));
-    lastTickAt = now;
+    updateApiWarningState();
+  }
 
-    try {
-      const idleSeconds = await invoke('get_idle_seconds');
-      const activeNow = idleSeconds < 2.0;
-      const missedSeconds = Math.max(0, elapsedSeconds - 1);
-      if (missedSeconds > 0) {
-        // Missed ticks are timing jitter/lag; keep minute accounting.
-        applySampleSeconds(0, missedSeconds);
-      }
-      applySampleSeconds(activeNow ? 1 : 0, activeNow ? 0 : 1);
+  function setHasApiKey(saved) {
+    hasApiKey = saved;
+    apiInput.placeholder = saved ? 'API Key saved (paste to replace)' : apiPlaceholder;
+  }
 
+  async function initApiKey() {
+    try {
+      setHasApiKey(Boolean(await invoke('load_api_token')));
     } catch (e) {
       console.error("Invoke Error:", e);
-      // If idle probe fails (e.g. during lock/unlock IPC hiccups),
-      // treat elapsed time as inactivity so recovery continues.
-      applySampleSeconds(0, elapsedSeconds);
     }
+    validateApiKey();
+  }
 
-  }, 1000);
-
-  async function sendAlert(typeOverride = null) {
-    const token = apiInput.value;
-    const type = typeOverride || modes[currentModeIndex];
-
-    if (!token || !token.trim()) {
-      console.warn("Alert Triggered but NO TOKEN set.");
-      apiKeyInvalid = true;
+  // Looks the key up right away; only a definite rejection flags it, network trouble doesn't.
+  async function validateApiKey() {
+    if (!hasApiKey) {
+      apiKeyRejected = false;
+      apiInput.title = '';
       updateApiWarningState();
       return;
     }
+    try {
+      const account = await invoke('validate_pavlok_token');
+      apiKeyRejected = false;
+      apiKeyInvalid = false;
+      apiInput.title = describeAccount(account);
+    } catch (e) {
+      console.error("API key check failed:", e);
+      apiKeyRejected = Boolean(e && (e.kind === 'unauthorized' || e.kind === 'invalidToken'));
+      apiInput.title = apiKeyRejected ? 'API key rejected by Pavlok' : '';
+    }
+    updateApiWarningState();
+  }
 
-    console.log(`Sending Alert: ${type}`);
+  function describeAccount(account) {
+    if (!account) return '';
+    const devices = (account.devices || [])
+      .map(d => [d.name || d.model || d.id, d.battery != null ? `${d.battery}%` : null].filter(Boolean).join(' '))
+      .filter(Boolean);
+    const who = account.name || account.email || 'Pavlok account';
+    return devices.length ? `${who} — ${devices.join(', ')}` : who;
+  }
+
+  // --- Snooze ---
+  function applySnooze(state) {
+    snoozeUntilMs = (state && state.untilMs) || null;
+    renderSnooze();
+  }
+
+  function renderSnooze() {
+    const remaining = snoozeUntilMs ? snoozeUntilMs - Date.now() : 0;
+    snoozeDisplay.hidden = remaining <= 0;
+    appCircle.classList.toggle('snoozed', remaining > 0);
+    if (remaining <= 0) return;
+    const total = Math.ceil(remaining / 1000);
+    const hours = Math.floor(total / 3600);
+    const minutes = Math.floor((total % 3600) / 60);
+    const seconds = `${total % 60}`.padStart(2, '0');
+    const clock = hours ? `${hours}:${`${minutes}`.padStart(2, '0')}:${seconds}` : `${minutes}:${seconds}`;
+    snoozeDisplay.textContent = `Snoozed ${clock}`;
+  }
+
+  // --- Stats ---
+  function openStats() {
+    statsView.hidden = false;
+    refreshStats();
+  }
+
+  // Local-time bounds of the current period (offset 0) or earlier ones; weeks start on Monday.
+  function periodBounds(range, offset) {
+    const start = new Date();
+    start.setHours(0, 0, 0, 0);
+    const days = range === 'week' ? 7 : 1;
+    if (range === 'week') start.setDate(start.getDate() - ((start.getDay() + 6) % 7));
+    start.setDate(start.getDate() + offset * days);
+    const end = new Date(start);
+    end.setDate(end.getDate() + days);
+    return { fromMs: start.getTime(), toMs: end.getTime() };
+  }
+
+  async function refreshStats() {
+    let current, previous;
     try {
-      const res = await invoke('send_pavlok_alert', {
-        token: token,
-        stimulusType: type
-      });
-      console.log("Alert Result:", res);
-      if (res === "Sent") {
-        apiKeyInvalid = false;
-      } else if (typeof res === "string" && /^Error:\s*(401|403)\b/.test(res)) {
-        apiKeyInvalid = true;
-      }
-      updateApiWarningState();
+      [current, previous] = await Promise.all([
+        invoke('get_stats', { range: periodBounds(statsRange, 0) }),
+        invoke('get_stats', { range: periodBounds(statsRange, -1) })
+      ]);
     } catch (e) {
-      console.error("Alert Failed:", e);
+      console.error("Invoke Error:", e);
+      return;
     }
+    if (!current || !previous) return;
+    statsCurrentLabel.textContent = statsRange === 'week' ? 'This week' : 'Today';
+    statsPreviousLabel.textContent = statsRange === 'week' ? 'Last week' : 'Yesterday';
+    const rows = [
+      ['Active', s => formatMinutes(s.activeMinutes)],
+      ['Longest stretch', s => formatMinutes(s.longestStretchMinutes)],
+      ['100% hits', s => `${s.limitHits}`],
+      ['Beep / Vibe / Zap', s => `${s.stimuli.beep} / ${s.stimuli.vibe} / ${s.stimuli.zap}`],
+      ['Breaks taken', s => s.breakCompliance == null ? '—' : `${Math.round(s.breakCompliance)}%`]
+    ];
+    statsBody.replaceChildren(...rows.map(([label, format]) => {
+      const row = document.createElement('tr');
+      [label, format(current), format(previous)].forEach((text, i) => {
+        const cell = document.createElement(i === 0 ? 'th' : 'td');
+        cell.textContent = text;
+        row.appendChild(cell);
+      });
+      return row;
+    }));
   }
 
-  async function sendDoubleVibroAlertOnce() {
-    await sendAlert('vibro');
-    await new Promise(resolve => setTimeout(resolve, 200));
-    await sendAlert('vibro');
+  function formatMinutes(minutes) {
+    const hours = Math.floor(minutes / 60);
+    return hours ? `${hours}h ${minutes % 60}m` : `${minutes}m`;
   }
 
   // --- Helpers ---
@@ -341,10 +495,7 @@ document.addEventListener('DOMContentLoaded', () => {
       if (val > 99) val = 99;
       input.value = val;
     }
-    if (input.id === 'work-timer') localStorage.setItem('workTime', val);
-    if (input.id === 'break-timer') localStorage.setItem('breakTime', val);
-
-    refreshFatigueUI();
+    syncLimits();
   }
 
   function updateModeUI() {
@@ -352,37 +503,18 @@ document.addEventListener('DOMContentLoaded', () => {
     Object.values(icons).forEach(icon => icon.classList.remove('active'));
     if (icons[mode]) icons[mode].classList.add('active');
     modeBtn.setAttribute('data-mode', mode);
-    modeBtn.title = capitalize(mode);
+    const level = intensities?.[mode === 'vibro' ? 'vibe' : mode];
+    modeBtn.title = level ? `${capitalize(mode)} (${level}%)` : capitalize(mode);
   }
 
-  function updateMonitoringState() {
-    refreshFatigueUI();
-
-    if (isMonitoring) {
-      // New run starts a fresh minute window; fatigue value is preserved.
-      activeSeconds = 0;
-      secondCounter = 0;
-      lastTickAt = Date.now();
-      appCircle.classList.add('monitoring');
-    } else {
-      lastTickAt = null;
-      appCircle.classList.remove('monitoring');
-    }
-  }
-
-  function resetFatigue() {
-    const previousFatigue = fatigue;
-    fatigue = 0;
-    restStreak = 0;
-    activeSeconds = 0;
-    secondCounter = 0;
-    lastAlertTime = 0;
-    lastTickAt = Date.now();
-    setProgress(0, 0);
-
-    if (previousFatigue > 0) {
-      sendDoubleVibroAlertOnce();
+  async function updateMonitoringState() {
+    try {
+      fatigueState = await invoke('set_monitoring', { enabled: isMonitoring }) || fatigueState;
+    } catch (e) {
+      console.error("Invoke Error:", e);
     }
+    refreshFatigueUI();
+    appCircle.classList.toggle('monitoring', isMonitoring);
   }
 
   function triggerHapticVisual(element) {
@@ -393,9 +525,11 @@ document.addEventListener('DOMContentLoaded', () => {
   }
 
   function updateBoltTooltip() {
-    const tooltip = (isAtLimit() && apiKeyInvalid)
-      ? "API key invalid: alert not sent"
-      : (isMonitoring ? "Stop" : "Start");
+    const tooltip = apiKeyRejected
+      ? "API key rejected by Pavlok"
+      : (isAtLimit() && apiKeyInvalid)
+        ? "API key invalid: alert not sent"
+        : (isMonitoring ? "Stop" : "Start");
     boltIcon.setAttribute("title", tooltip);
     boltIcon.setAttribute("aria-label", tooltip);
   }